@@ -0,0 +1,98 @@
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::Value;
+
+use crate::Config;
+
+#[derive(Debug)]
+pub struct ClashApi {
+    pub address: String,
+    pub secret: String,
+    pub external_ui: Option<String>,
+}
+
+impl ClashApi {
+    /// Dashboard with the controller pre-filled, the bundled UI if any, else a hosted yacd.
+    pub fn dashboard_url(&self) -> String {
+        let (host, port) = self
+            .address
+            .rsplit_once(':')
+            .unwrap_or((&self.address, "9090"));
+        let host = match host {
+            "0.0.0.0" | "" => "127.0.0.1",
+            "[::]" => "[::1]",
+            host => host,
+        };
+        let mut query = format!("hostname={}&port={port}", host.trim_matches(['[', ']']));
+        if !self.secret.is_empty() {
+            let secret: String =
+                url::form_urlencoded::byte_serialize(self.secret.as_bytes()).collect();
+            query.push_str(&format!("&secret={secret}"));
+        }
+        match self.external_ui {
+            Some(_) => format!("http://{host}:{port}/ui/?{query}"),
+            None => format!("https://yacd.metacubex.one/?{query}"),
+        }
+    }
+}
+
+pub fn inject_clash_api(
+    data: &mut Config,
+    address: &str,
+    secret: Option<String>,
+    external_ui: Option<&str>,
+) -> ClashApi {
+    let secret = secret.unwrap_or_else(|| {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect()
+    });
+
+    let mut clash_api = serde_json::Map::new();
+    clash_api.insert(
+        String::from("external_controller"),
+        Value::String(address.to_string()),
+    );
+    clash_api.insert(String::from("secret"), Value::String(secret.clone()));
+    if let Some(external_ui) = external_ui {
+        clash_api.insert(
+            String::from("external_ui"),
+            Value::String(external_ui.to_string()),
+        );
+    }
+
+    let experimental = data
+        .entry(String::from("experimental"))
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    if !experimental.is_object() {
+        *experimental = Value::Object(serde_json::Map::new());
+    }
+    experimental
+        .as_object_mut()
+        .unwrap()
+        .insert(String::from("clash_api"), Value::Object(clash_api));
+
+    ClashApi {
+        address: address.to_string(),
+        secret,
+        external_ui: external_ui.map(String::from),
+    }
+}
+
+/// The clash API a config already enables, if any.
+pub fn find_clash_api(data: &Config) -> Option<ClashApi> {
+    let clash_api = data.get("experimental")?.get("clash_api")?;
+    Some(ClashApi {
+        address: clash_api.get("external_controller")?.as_str()?.to_string(),
+        secret: clash_api
+            .get("secret")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        external_ui: clash_api
+            .get("external_ui")
+            .and_then(Value::as_str)
+            .map(String::from),
+    })
+}
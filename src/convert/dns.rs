@@ -0,0 +1,56 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsPreset {
+    Cn,
+    Global,
+    Fakeip,
+}
+
+pub fn dns_preset(preset: DnsPreset) -> Value {
+    match preset {
+        DnsPreset::Cn => serde_json::json!({
+            "servers": [
+                { "tag": "dns-remote", "address": "https://1.1.1.1/dns-query" },
+                { "tag": "dns-local", "address": "https://223.5.5.5/dns-query" },
+            ],
+            "rules": [
+                { "outbound": "any", "server": "dns-local" },
+                { "domain_suffix": [".cn"], "server": "dns-local" },
+            ],
+            "final": "dns-remote",
+            "strategy": "ipv4_only",
+        }),
+        DnsPreset::Global => serde_json::json!({
+            "servers": [
+                { "tag": "dns-remote", "address": "https://1.1.1.1/dns-query" },
+                { "tag": "dns-local", "address": "local" },
+            ],
+            "rules": [
+                { "outbound": "any", "server": "dns-local" },
+            ],
+            "final": "dns-remote",
+        }),
+        DnsPreset::Fakeip => serde_json::json!({
+            "servers": [
+                { "tag": "dns-remote", "address": "https://1.1.1.1/dns-query" },
+                { "tag": "dns-local", "address": "https://223.5.5.5/dns-query" },
+                { "tag": "dns-fakeip", "address": "fakeip" },
+            ],
+            "rules": [
+                { "outbound": "any", "server": "dns-local" },
+                { "query_type": ["A", "AAAA"], "server": "dns-fakeip" },
+            ],
+            "fakeip": {
+                "enabled": true,
+                "inet4_range": "198.18.0.0/15",
+                "inet6_range": "fc00::/18",
+            },
+            "final": "dns-remote",
+            "independent_cache": true,
+        }),
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashMap, net::IpAddr, path::Path};
+
+use futures_util::future::join_all;
+use maxminddb::{geoip2, Reader};
+use tokio::net::lookup_host;
+
+use super::{node::Node, outbounds::rename_outbounds};
+use crate::{Config, Error, Result};
+
+/// Regional indicator pair for an ISO 3166 code, e.g. `US` -> 🇺🇸.
+pub fn country_flag(code: &str) -> String {
+    code.chars()
+        .filter_map(|c| {
+            char::from_u32(0x1F1E6 + (c.to_ascii_uppercase() as u32).checked_sub('A' as u32)?)
+        })
+        .collect()
+}
+
+async fn resolve(node: &Node) -> Option<IpAddr> {
+    let server = node.server.as_deref()?;
+    if let Ok(ip) = server.parse() {
+        return Some(ip);
+    }
+    Some(lookup_host((server, 0)).await.ok()?.next()?.ip())
+}
+
+fn lookup_country(reader: &Reader<Vec<u8>>, ip: IpAddr) -> Option<String> {
+    let country: geoip2::Country = reader.lookup(ip).ok()?.decode().ok()??;
+    country.country.iso_code.map(String::from)
+}
+
+/// Look up the exit country of every node and prefix its tag with the country's flag.
+///
+/// Returns the country code by new tag, for nodes whose server could be located.
+pub async fn apply_geoip(data: &mut Config, database: &Path) -> Result<HashMap<String, String>> {
+    let reader = Reader::open_readfile(database).map_err(|e| {
+        Error::Parse(format!(
+            "Can't read GeoIP database {}: {e}",
+            database.display()
+        ))
+    })?;
+    let nodes: Vec<Node> = data
+        .get("outbounds")
+        .and_then(|outbounds| outbounds.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+        .collect();
+    let addresses = join_all(nodes.iter().map(resolve)).await;
+
+    let mut renames = HashMap::new();
+    let mut countries = HashMap::new();
+    for (node, address) in nodes.iter().zip(addresses) {
+        let Some(country) = address.and_then(|ip| lookup_country(&reader, ip)) else {
+            continue;
+        };
+        let flag = country_flag(&country);
+        let mut tag = node.tag.clone();
+        if !tag.starts_with(&flag) {
+            tag = format!("{flag} {tag}");
+            renames.insert(node.tag.clone(), tag.clone());
+        }
+        countries.insert(tag, country);
+    }
+    rename_outbounds(data, &renames);
+    Ok(countries)
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::node::{is_node, node_region};
+use crate::Config;
+
+/// Region groups use `countries` by node tag, falling back to guessing from the tag.
+pub fn generate_groups(
+    data: &mut Config,
+    region_groups: bool,
+    countries: &HashMap<String, String>,
+) -> usize {
+    let Some(outbounds) = data.get_mut("outbounds").and_then(Value::as_array_mut) else {
+        return 0;
+    };
+    let has_groups = outbounds.iter().any(|outbound| {
+        outbound
+            .get("type")
+            .and_then(Value::as_str)
+            .is_some_and(|t| t == "selector" || t == "urltest")
+    });
+    if has_groups {
+        return 0;
+    }
+
+    let nodes: Vec<String> = outbounds
+        .iter()
+        .filter(|outbound| is_node(outbound))
+        .filter_map(|outbound| outbound.get("tag").and_then(Value::as_str))
+        .map(String::from)
+        .collect();
+    if nodes.is_empty() {
+        return 0;
+    }
+
+    let mut groups = Vec::new();
+    let mut choices = vec![String::from("auto")];
+    if region_groups {
+        let mut regions: Vec<(&str, Vec<String>)> = Vec::new();
+        for node in &nodes {
+            let Some(region) = countries
+                .get(node)
+                .map(String::as_str)
+                .or_else(|| node_region(node))
+            else {
+                continue;
+            };
+            match regions.iter_mut().find(|(r, _)| *r == region) {
+                Some((_, members)) => members.push(node.clone()),
+                None => regions.push((region, vec![node.clone()])),
+            }
+        }
+        for (region, members) in regions {
+            let tag = format!("auto-{}", region.to_lowercase());
+            groups.push(serde_json::json!({
+                "type": "urltest",
+                "tag": tag,
+                "outbounds": members,
+            }));
+            choices.push(tag);
+        }
+    }
+    choices.extend(nodes.iter().cloned());
+
+    groups.insert(
+        0,
+        serde_json::json!({
+            "type": "urltest",
+            "tag": "auto",
+            "outbounds": nodes,
+        }),
+    );
+    groups.insert(
+        0,
+        serde_json::json!({
+            "type": "selector",
+            "tag": "proxy",
+            "outbounds": choices,
+            "default": "auto",
+        }),
+    );
+
+    let count = groups.len();
+    outbounds.splice(0..0, groups);
+    count
+}
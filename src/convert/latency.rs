@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::join_all;
+use tokio::net::{lookup_host, TcpStream};
+
+use super::{
+    node::Node,
+    outbounds::{remove_outbounds, rename_outbounds},
+};
+use crate::Config;
+
+/// How nodes are probed and what happens to slow ones.
+#[derive(Debug, Clone)]
+pub struct LatencyOptions {
+    /// Give up on a node after this long.
+    pub timeout: Duration,
+    /// Drop nodes slower than this, otherwise every tag gets its latency appended.
+    pub max: Option<Duration>,
+}
+
+/// TCP connect time to the node's server, `None` when unreachable.
+async fn probe(node: &Node, timeout: Duration) -> Option<Duration> {
+    let (server, port) = (node.server.as_deref()?, node.server_port?);
+    let connect = async {
+        // Resolve first so only the handshake is timed.
+        let address = lookup_host((server, port)).await.ok()?.next()?;
+        let start = Instant::now();
+        TcpStream::connect(address).await.ok()?;
+        Some(start.elapsed())
+    };
+    tokio::time::timeout(timeout, connect).await.ok().flatten()
+}
+
+/// Probe every node concurrently, in node order.
+pub async fn probe_latency(nodes: &[Node], timeout: Duration) -> Vec<Option<Duration>> {
+    join_all(nodes.iter().map(|node| probe(node, timeout))).await
+}
+
+/// Tag of a node annotated with its latency.
+pub fn latency_tag(tag: &str, latency: Option<Duration>) -> String {
+    match latency {
+        Some(latency) => format!("{tag} [{}ms]", latency.as_millis()),
+        None => format!("{tag} [timeout]"),
+    }
+}
+
+/// Probe all nodes, then annotate their tags or drop the slow ones.
+///
+/// Returns the latency of every probed node by its original tag.
+pub async fn apply_latency(
+    data: &mut Config,
+    options: &LatencyOptions,
+) -> Vec<(String, Option<Duration>)> {
+    let nodes: Vec<Node> = data
+        .get("outbounds")
+        .and_then(|outbounds| outbounds.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+        .collect();
+    let latencies = probe_latency(&nodes, options.timeout).await;
+    let results: Vec<(String, Option<Duration>)> = nodes
+        .into_iter()
+        .map(|node| node.tag)
+        .zip(latencies)
+        .collect();
+
+    match options.max {
+        Some(max) => {
+            let slow: Vec<String> = results
+                .iter()
+                .filter(|(_, latency)| latency.is_none_or(|latency| latency > max))
+                .map(|(tag, _)| tag.clone())
+                .collect();
+            remove_outbounds(data, &slow);
+        }
+        None => {
+            let renames: HashMap<String, String> = results
+                .iter()
+                .map(|(tag, latency)| (tag.clone(), latency_tag(tag, *latency)))
+                .collect();
+            rename_outbounds(data, &renames);
+        }
+    }
+    results
+}
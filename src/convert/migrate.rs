@@ -0,0 +1,249 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::outbounds::remove_outbounds;
+use crate::Config;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Version(pub u64, pub u64, pub u64);
+
+impl TryFrom<String> for Version {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().trim_start_matches('v').split(['.', '-']);
+        let mut next = || -> Result<u64, String> {
+            parts
+                .next()
+                .unwrap_or("0")
+                .parse()
+                .map_err(|_| format!("Invalid version: {s}"))
+        };
+        Ok(Version(next()?, next()?, next()?))
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+pub(super) fn geo_rule_set(kind: &str, name: &str) -> Value {
+    serde_json::json!({
+        "type": "remote",
+        "tag": format!("{kind}-{name}"),
+        "format": "binary",
+        "url": format!(
+            "https://raw.githubusercontent.com/SagerNet/sing-{kind}/rule-set/{kind}-{name}.srs"
+        ),
+    })
+}
+
+/// Rewrite geosite/geoip matchers of a (possibly logical) rule into rule_set references.
+fn migrate_geo_rule(rule: &mut Value, rule_sets: &mut Vec<Value>) {
+    let Some(rule) = rule.as_object_mut() else {
+        return;
+    };
+    if let Some(rules) = rule.get_mut("rules").and_then(Value::as_array_mut) {
+        for rule in rules {
+            migrate_geo_rule(rule, rule_sets);
+        }
+    }
+
+    let mut tags = Vec::new();
+    for (field, kind) in [
+        ("geosite", "geosite"),
+        ("geoip", "geoip"),
+        ("source_geoip", "geoip"),
+    ] {
+        let names: Vec<String> = match rule.remove(field) {
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect(),
+            _ => continue,
+        };
+        if field == "source_geoip" {
+            rule.insert(
+                String::from("rule_set_ip_cidr_match_source"),
+                Value::Bool(true),
+            );
+        }
+        for name in names {
+            if kind == "geoip" && name == "private" {
+                rule.insert(String::from("ip_is_private"), Value::Bool(true));
+                continue;
+            }
+            let rule_set = geo_rule_set(kind, &name);
+            if !rule_sets
+                .iter()
+                .any(|r| r.get("tag") == rule_set.get("tag"))
+            {
+                rule_sets.push(rule_set.clone());
+            }
+            tags.push(rule_set["tag"].clone());
+        }
+    }
+    if tags.is_empty() {
+        return;
+    }
+
+    let references = rule
+        .entry(String::from("rule_set"))
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Value::String(tag) = references {
+        *references = Value::Array(vec![Value::String(tag.clone())]);
+    }
+    if let Some(references) = references.as_array_mut() {
+        references.extend(tags);
+    }
+}
+
+/// Split `geoip: ["private", ...]` into two rules, `ip_is_private` can't be OR-ed with rule_set.
+fn split_private_geoip(rules: &mut Vec<Value>) {
+    let mut index = 0;
+    while index < rules.len() {
+        let Some(names) = rules[index].get_mut("geoip").and_then(Value::as_array_mut) else {
+            index += 1;
+            continue;
+        };
+        if names.len() > 1 && names.iter().any(|name| name == "private") {
+            names.retain(|name| name != "private");
+            let mut private = rules[index].clone();
+            private["geoip"] = Value::String(String::from("private"));
+            rules.insert(index, private);
+            index += 1;
+        }
+        index += 1;
+    }
+}
+
+fn migrate_geo_resources(data: &mut Config) {
+    let mut rule_sets = Vec::new();
+    for section in ["route", "dns"] {
+        if let Some(rules) = data
+            .get_mut(section)
+            .and_then(|section| section.get_mut("rules"))
+            .and_then(Value::as_array_mut)
+        {
+            split_private_geoip(rules);
+            for rule in rules {
+                migrate_geo_rule(rule, &mut rule_sets);
+            }
+        }
+    }
+
+    let Some(route) = data.get_mut("route").and_then(Value::as_object_mut) else {
+        return;
+    };
+    route.remove("geoip");
+    route.remove("geosite");
+    if rule_sets.is_empty() {
+        return;
+    }
+    let existing = route
+        .entry(String::from("rule_set"))
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(existing) = existing.as_array_mut() {
+        for rule_set in rule_sets {
+            if !existing.iter().any(|r| r.get("tag") == rule_set.get("tag")) {
+                existing.push(rule_set);
+            }
+        }
+    }
+}
+
+/// Replace legacy special outbounds and inbound fields with rule actions (sing-box 1.11).
+fn migrate_rule_actions(data: &mut Config) {
+    let mut actions = Vec::new();
+    if let Some(inbounds) = data.get_mut("inbounds").and_then(Value::as_array_mut) {
+        for inbound in inbounds.iter_mut().filter_map(Value::as_object_mut) {
+            let tag = inbound.get("tag").cloned();
+            let sniff = inbound.remove("sniff").and_then(|v| v.as_bool());
+            inbound.remove("sniff_override_destination");
+            inbound.remove("sniff_timeout");
+            let strategy = inbound.remove("domain_strategy");
+            let Some(tag) = tag else {
+                continue;
+            };
+            if sniff == Some(true) {
+                actions.push(serde_json::json!({ "inbound": tag, "action": "sniff" }));
+            }
+            if let Some(strategy) = strategy {
+                actions.push(serde_json::json!({
+                    "inbound": tag,
+                    "action": "resolve",
+                    "strategy": strategy,
+                }));
+            }
+        }
+    }
+
+    let legacy: Vec<(String, &str)> = data
+        .get("outbounds")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|outbound| {
+            let action = match outbound.get("type").and_then(Value::as_str)? {
+                "block" => "reject",
+                "dns" => "hijack-dns",
+                _ => return None,
+            };
+            Some((outbound.get("tag")?.as_str()?.to_string(), action))
+        })
+        .collect();
+    if actions.is_empty() && legacy.is_empty() {
+        return;
+    }
+
+    let rules = data
+        .entry(String::from("route"))
+        .or_insert_with(|| Value::Object(serde_json::Map::new()))
+        .as_object_mut()
+        .map(|route| {
+            route
+                .entry(String::from("rules"))
+                .or_insert_with(|| Value::Array(Vec::new()))
+        })
+        .and_then(Value::as_array_mut);
+    if let Some(rules) = rules {
+        for rule in rules.iter_mut().filter_map(Value::as_object_mut) {
+            let outbound = rule.get("outbound").and_then(Value::as_str);
+            if let Some((_, action)) = legacy
+                .iter()
+                .find(|(tag, _)| Some(tag.as_str()) == outbound)
+            {
+                rule.remove("outbound");
+                rule.insert(String::from("action"), Value::String(action.to_string()));
+            }
+        }
+        rules.splice(0..0, actions);
+    }
+
+    // Rules point at actions now, drop the outbounds from groups, defaults and `final`,
+    // along with groups left empty.
+    let tags: Vec<String> = legacy.into_iter().map(|(tag, _)| tag).collect();
+    remove_outbounds(data, &tags);
+}
+
+pub fn migrate_config(data: &mut Config, version: Version) {
+    if version >= Version(1, 8, 0) {
+        migrate_geo_resources(data);
+    }
+    if version >= Version(1, 11, 0) {
+        migrate_rule_actions(data);
+    }
+}
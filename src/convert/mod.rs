@@ -0,0 +1,441 @@
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    source::{download_geoip, download_rule_sets, FetchOptions},
+    Config, Error, Result, Subscription,
+};
+
+mod clash_api;
+mod dns;
+mod geoip;
+mod groups;
+mod latency;
+mod migrate;
+mod node;
+mod outbounds;
+mod patch;
+mod ports;
+mod rename;
+mod route;
+mod sort;
+mod strip;
+mod summary;
+mod template;
+mod validate;
+
+pub use clash_api::{find_clash_api, inject_clash_api, ClashApi};
+pub use dns::{dns_preset, DnsPreset};
+pub use geoip::{apply_geoip, country_flag};
+pub use groups::generate_groups;
+pub use latency::{apply_latency, latency_tag, probe_latency, LatencyOptions};
+pub use migrate::{migrate_config, Version};
+pub use node::{node_region, Node};
+pub use outbounds::{keep_protocols, remove_outbounds, rename_outbounds};
+pub use patch::{apply_patch, load_patch};
+pub(crate) use ports::bindable;
+pub use ports::{bare_host, inbound_port, move_inbound_port, port_conflict, socket_address};
+pub use rename::RenameMap;
+use route::has_rule_actions;
+pub use route::{apply_route_preset, enable_inbound_sniffing, RoutePreset};
+pub use sort::{sort_outbounds, SortKey};
+pub use strip::{strip_sections, STRIPPABLE};
+pub use summary::{Dropped, Summary};
+pub use template::{apply_overrides, apply_template, deep_merge, load_overrides, load_template};
+pub use validate::validate_config;
+
+#[derive(Debug, Clone)]
+pub struct ClashApiOptions {
+    pub address: String,
+    pub secret: Option<String>,
+    pub external_ui: Option<String>,
+}
+
+/// Transformation passes applied to a fetched subscription, in field order.
+#[derive(Debug, Default)]
+pub struct Converter {
+    /// Subscription sections removed before anything else is applied.
+    pub strip: Vec<String>,
+    pub template: Option<Config>,
+    pub dns: Option<DnsPreset>,
+    /// Keep only nodes of these outbound types, all when empty.
+    pub protocols: Vec<String>,
+    /// Node tags replaced everywhere they are referenced.
+    pub rename_map: Option<RenameMap>,
+    /// Country database tagging nodes by their resolved server, downloaded if missing.
+    pub geoip: Option<PathBuf>,
+    /// Probe nodes and annotate or drop them by latency.
+    pub latency: Option<LatencyOptions>,
+    pub sort: Option<SortKey>,
+    pub groups: bool,
+    pub region_groups: bool,
+    /// Curated route section, pointing at the groups when generated.
+    pub route: Option<RoutePreset>,
+    pub block_ads: bool,
+    pub target_version: Option<Version>,
+    /// Download remote rule-sets into this directory.
+    pub rule_set_dir: Option<PathBuf>,
+    pub fetch: FetchOptions,
+    pub clash_api: Option<ClashApiOptions>,
+    /// Local customizations merged over the generated config, flags still win.
+    pub overrides: Option<Config>,
+    /// Mixed inbound added when the subscription has none, [`DEFAULT_INBOUND`] if unset.
+    pub default_inbound: Option<SocketAddr>,
+    /// Tag of the mixed inbound backing the external proxy, the first one if unset.
+    pub inbound_tag: Option<String>,
+    /// Listen address and port forced onto the mixed inbound.
+    pub listen: Option<String>,
+    pub port: Option<u16>,
+    /// RFC 6902 operations applied last.
+    pub patch: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct Conversion {
+    pub config: Config,
+    pub controller: ExternalController,
+    pub clash_api: Option<ClashApi>,
+    /// Measured latency by original node tag, empty unless probing was enabled.
+    pub latency: Vec<(String, Option<Duration>)>,
+    /// Nodes removed by the protocol filter or latency limit.
+    pub dropped: Vec<Dropped>,
+}
+
+impl Converter {
+    pub async fn convert(&self, subscription: Subscription) -> Result<Conversion> {
+        let mut config = subscription.config;
+
+        if !self.strip.is_empty() {
+            strip_sections(&mut config, &self.strip)?;
+            debug!("Stripped sections: {:?}", self.strip);
+        }
+        if let Some(template) = &self.template {
+            config = apply_template(config, template.clone());
+            debug!("Applied template with sections: {:?}", template.keys());
+        }
+        if let Some(preset) = self.dns {
+            config.insert(String::from("dns"), dns_preset(preset));
+            debug!("Replaced dns with the {preset:?} preset");
+        }
+        let mut dropped = Vec::new();
+        if !self.protocols.is_empty() {
+            let nodes = config
+                .get("outbounds")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Node::from_outbound)
+                .map(|node| (node.tag, node.protocol))
+                .collect::<HashMap<_, _>>();
+            let removed = keep_protocols(&mut config, &self.protocols);
+            debug!("Removed {} outbounds of other protocols", removed.len());
+            for tag in removed {
+                if let Some(protocol) = nodes.get(&tag) {
+                    dropped.push(Dropped::new(tag, format!("protocol {protocol} not kept")));
+                }
+            }
+        }
+        if let Some(map) = &self.rename_map {
+            let renames = map.renames(&config);
+            rename_outbounds(&mut config, &renames);
+            debug!("Renamed {} nodes", renames.len());
+        }
+        let mut countries = HashMap::new();
+        if let Some(database) = &self.geoip {
+            download_geoip(database, &self.fetch).await?;
+            countries = apply_geoip(&mut config, database).await?;
+            debug!("Located {} nodes by GeoIP", countries.len());
+        }
+        let mut latency = Vec::new();
+        // Latency of the nodes still present, by their current tag.
+        let mut latency_by_tag = HashMap::new();
+        if let Some(options) = &self.latency {
+            latency = apply_latency(&mut config, options).await;
+            debug!("Probed latency of {} nodes", latency.len());
+            for (tag, measured) in &latency {
+                if let Some(max) = options.max {
+                    match measured {
+                        None => dropped.push(Dropped::new(tag, "unreachable")),
+                        Some(measured) if *measured > max => dropped.push(Dropped::new(
+                            tag,
+                            format!(
+                                "{}ms slower than {}ms",
+                                measured.as_millis(),
+                                max.as_millis()
+                            ),
+                        )),
+                        Some(_) => {}
+                    }
+                }
+                let current = match options.max {
+                    Some(_) => tag.clone(),
+                    None => latency_tag(tag, *measured),
+                };
+                if let Some(country) = countries.remove(tag) {
+                    countries.insert(current.clone(), country);
+                }
+                if let Some(measured) = measured {
+                    latency_by_tag.insert(current, *measured);
+                }
+            }
+        }
+        if let Some(key) = self.sort {
+            sort_outbounds(&mut config, key, &countries, &latency_by_tag);
+            debug!("Sorted nodes by {key:?}");
+        }
+        if self.groups {
+            generate_groups(&mut config, self.region_groups, &countries);
+            debug!("Generated groups, region groups: {}", self.region_groups);
+        }
+        if let Some(preset) = self.route {
+            apply_route_preset(&mut config, preset, self.block_ads, self.target_version);
+            debug!(
+                "Applied route preset {preset:?}, ad blocking: {}",
+                self.block_ads
+            );
+        }
+        if let Some(version) = self.target_version {
+            migrate_config(&mut config, version);
+            debug!("Migrated config for sing-box {version}");
+        }
+        if let Some(rule_set_dir) = &self.rule_set_dir {
+            let count = download_rule_sets(&mut config, rule_set_dir, &self.fetch).await?;
+            debug!("Downloaded {count} rule-sets to {}", rule_set_dir.display());
+        }
+        let clash_api = self.clash_api.as_ref().map(|options| {
+            inject_clash_api(
+                &mut config,
+                &options.address,
+                options.secret.clone(),
+                options.external_ui.as_deref(),
+            )
+        });
+
+        if let Some(clash_api) = &clash_api {
+            debug!("Injected clash API on {}", clash_api.address);
+        }
+
+        if let Some(overrides) = &self.overrides {
+            apply_overrides(&mut config, overrides.clone());
+            debug!("Merged overrides with sections: {:?}", overrides.keys());
+        }
+        let address = self.default_inbound.unwrap_or(DEFAULT_INBOUND);
+        if ensure_mixed_inbound(&mut config, address) {
+            debug!("Added a mixed inbound on {address}, the subscription has none");
+        }
+        select_mixed_inbound(&mut config, self.inbound_tag.as_deref())?;
+        if self.listen.is_some() || self.port.is_some() {
+            override_inbound(&mut config, self.listen.as_deref(), self.port);
+            debug!(
+                "Overrode mixed inbound with {:?}:{:?}",
+                self.listen, self.port
+            );
+        }
+        if self.route.is_some() && !has_rule_actions(self.target_version) {
+            // After the inbounds are settled, so the one added above sniffs too.
+            enable_inbound_sniffing(&mut config);
+        }
+        let mut controller = convert_inbounds(&mut config)?;
+        debug!(
+            "Kept mixed inbound {}",
+            socket_address(&controller.address, &controller.port)
+        );
+
+        if !self.patch.is_empty() {
+            let mut doc = Value::Object(config.into_iter().collect());
+            apply_patch(&mut doc, &self.patch)?;
+            debug!("Applied {} patch operations", self.patch.len());
+            config = serde_json::from_value(doc)
+                .map_err(|e| Error::Validation(format!("Patched config is not an object: {e}")))?;
+            // The patch may have moved the mixed inbound the Surge line points at.
+            if let Some((listen, port)) = inbound_port(&config) {
+                controller.address = connect_address(&listen).to_string();
+                controller.port = port.to_string();
+            }
+        }
+
+        Ok(Conversion {
+            config,
+            controller,
+            clash_api,
+            latency,
+            dropped,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExternalController {
+    pub address: String,
+    pub port: String,
+}
+
+/// Mixed inbound used when the subscription has none.
+pub const DEFAULT_INBOUND: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 2080));
+
+fn mixed_index(inbounds: &[Value]) -> Option<usize> {
+    inbounds
+        .iter()
+        .position(|inbound| inbound.get("type").and_then(Value::as_str) == Some("mixed"))
+}
+
+/// Add a mixed inbound on `address` unless the config already has one.
+///
+/// Returns whether one was added.
+pub fn ensure_mixed_inbound(data: &mut Config, address: SocketAddr) -> bool {
+    let mixed = serde_json::json!({
+        "type": "mixed",
+        "tag": "mixed-in",
+        "listen": address.ip().to_string(),
+        "listen_port": address.port(),
+    });
+    let inbounds = data
+        .entry(String::from("inbounds"))
+        .or_insert_with(|| Value::Array(Vec::new()));
+    match inbounds.as_array_mut() {
+        Some(inbounds) if mixed_index(inbounds).is_some() => false,
+        Some(inbounds) => {
+            inbounds.push(mixed);
+            true
+        }
+        None => {
+            *inbounds = Value::Array(vec![mixed]);
+            true
+        }
+    }
+}
+
+/// Move the mixed inbound tagged `tag`, or else the first one, to the front of the
+/// inbounds, where everything looking for "the" mixed inbound finds it.
+pub fn select_mixed_inbound(data: &mut Config, tag: Option<&str>) -> Result<()> {
+    let Some(inbounds) = data.get_mut("inbounds").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+    let candidates: Vec<(usize, String)> = inbounds
+        .iter()
+        .enumerate()
+        .filter(|(_, inbound)| inbound.get("type").and_then(Value::as_str) == Some("mixed"))
+        .map(|(index, inbound)| {
+            let tag = inbound.get("tag").and_then(Value::as_str).unwrap_or("");
+            let listen = inbound
+                .get("listen")
+                .and_then(Value::as_str)
+                .unwrap_or("127.0.0.1");
+            let port = inbound.get("listen_port").cloned().unwrap_or_default();
+            (index, format!("{tag} ({})", socket_address(listen, port)))
+        })
+        .collect();
+    let list = || {
+        candidates
+            .iter()
+            .map(|(_, candidate)| candidate.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let chosen = match tag {
+        Some(tag) => candidates
+            .iter()
+            .find(|(index, _)| inbounds[*index].get("tag").and_then(Value::as_str) == Some(tag))
+            .map(|(index, _)| *index)
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "No mixed inbound tagged {tag}, candidates: {}",
+                    list()
+                ))
+            })?,
+        None => {
+            let Some((index, _)) = candidates.first() else {
+                return Ok(());
+            };
+            if candidates.len() > 1 {
+                warn!(
+                    "✖ Found {} mixed inbounds, using the first, pass --inbound-tag to choose: {}",
+                    candidates.len(),
+                    list()
+                );
+            }
+            *index
+        }
+    };
+    let inbound = inbounds.remove(chosen);
+    inbounds.insert(0, inbound);
+    Ok(())
+}
+
+/// Point the first mixed inbound at `listen` and `port`.
+pub fn override_inbound(data: &mut Config, listen: Option<&str>, port: Option<u16>) {
+    let Some(inbounds) = data.get_mut("inbounds").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let Some(index) = mixed_index(inbounds) else {
+        return;
+    };
+    let mixed = &mut inbounds[index];
+    if let Some(listen) = listen {
+        mixed["listen"] = Value::String(bare_host(listen).to_string());
+    }
+    if let Some(port) = port {
+        mixed["listen_port"] = Value::from(port);
+    }
+}
+
+/// Where clients reach an inbound listening on `listen`, loopback for the wildcard addresses.
+fn connect_address(listen: &str) -> &str {
+    match listen {
+        "" | "0.0.0.0" => "127.0.0.1",
+        "::" => "::1",
+        listen => listen,
+    }
+}
+
+pub fn convert_inbounds(data: &mut Config) -> Result<ExternalController> {
+    let inbounds = data
+        .get("inbounds")
+        .and_then(Value::as_array)
+        .ok_or_else(|| {
+            Error::Validation(String::from(
+                "Can't find any inbounds in target configuration.",
+            ))
+        })?;
+
+    let mut controller_info = ExternalController::default();
+    let mut new_inbound = Vec::new();
+    for inbound in inbounds {
+        if inbound.get("type").and_then(Value::as_str) != Some("mixed") {
+            continue;
+        }
+        let listen = inbound
+            .get("listen")
+            .and_then(Value::as_str)
+            .map(bare_host)
+            .unwrap_or("127.0.0.1");
+        // The selected inbound comes first.
+        if controller_info.port.is_empty() {
+            let port = inbound
+                .get("listen_port")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| {
+                    Error::Parse(format!(
+                        "Mixed inbound without a valid listen_port: {inbound}"
+                    ))
+                })?;
+            controller_info.address = connect_address(listen).to_string();
+            controller_info.port = port.to_string();
+        }
+        let mut inbound = inbound.clone();
+        // sing-box wants `::1`, not the bracketed url form `[::1]`.
+        inbound["listen"] = Value::String(listen.to_string());
+        new_inbound.push(inbound);
+    }
+
+    data.insert(String::from("inbounds"), Value::Array(new_inbound));
+    Ok(controller_info)
+}
@@ -0,0 +1,71 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+pub const NON_NODE_TYPES: [&str; 5] = ["direct", "block", "dns", "selector", "urltest"];
+
+static REGIONS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    [
+        ("HK", r"🇭🇰|\bhk(?:\b|\d)|hong ?kong|香港"),
+        ("TW", r"🇹🇼|\btw(?:\b|\d)|taiwan|台湾"),
+        ("JP", r"🇯🇵|\bjp(?:\b|\d)|japan|日本"),
+        ("SG", r"🇸🇬|\bsg(?:\b|\d)|singapore|新加坡"),
+        ("KR", r"🇰🇷|\bkr(?:\b|\d)|korea|韩国"),
+        ("US", r"🇺🇸|\bus(?:\b|\d)|united states|america|美国"),
+        ("GB", r"🇬🇧|\b(?:uk|gb)(?:\b|\d)|united kingdom|英国"),
+        ("DE", r"🇩🇪|\bde(?:\b|\d)|germany|德国"),
+    ]
+    .into_iter()
+    .map(|(region, pattern)| (region, Regex::new(&format!("(?i){pattern}")).unwrap()))
+    .collect()
+});
+
+pub fn node_region(tag: &str) -> Option<&'static str> {
+    REGIONS
+        .iter()
+        .find(|(_, pattern)| pattern.is_match(tag))
+        .map(|(region, _)| *region)
+}
+
+pub fn is_node(outbound: &Value) -> bool {
+    outbound
+        .get("type")
+        .and_then(Value::as_str)
+        .is_some_and(|t| !NON_NODE_TYPES.contains(&t))
+}
+
+/// A proxy server outbound, as opposed to groups and special outbounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub tag: String,
+    pub protocol: String,
+    pub server: Option<String>,
+    pub server_port: Option<u16>,
+    pub outbound: Value,
+}
+
+impl Node {
+    pub fn from_outbound(outbound: &Value) -> Option<Node> {
+        if !is_node(outbound) {
+            return None;
+        }
+        Some(Node {
+            tag: outbound.get("tag")?.as_str()?.to_string(),
+            protocol: outbound.get("type")?.as_str()?.to_string(),
+            server: outbound
+                .get("server")
+                .and_then(Value::as_str)
+                .map(String::from),
+            server_port: outbound
+                .get("server_port")
+                .and_then(Value::as_u64)
+                .and_then(|port| u16::try_from(port).ok()),
+            outbound: outbound.clone(),
+        })
+    }
+
+    pub fn region(&self) -> Option<&'static str> {
+        node_region(&self.tag)
+    }
+}
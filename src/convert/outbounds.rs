@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::node::Node;
+use crate::Config;
+
+fn outbounds_mut(data: &mut Config) -> Option<&mut Vec<Value>> {
+    data.get_mut("outbounds").and_then(Value::as_array_mut)
+}
+
+fn rename_value(value: &mut Value, renames: &HashMap<String, String>) {
+    if let Some(renamed) = value.as_str().and_then(|tag| renames.get(tag)) {
+        *value = Value::String(renamed.clone());
+    }
+}
+
+/// Rename outbounds, updating group members, group defaults, detours and the route and
+/// DNS references.
+pub fn rename_outbounds(data: &mut Config, renames: &HashMap<String, String>) {
+    if renames.is_empty() {
+        return;
+    }
+    if let Some(outbounds) = outbounds_mut(data) {
+        for outbound in outbounds {
+            for field in ["tag", "default", "detour"] {
+                if let Some(value) = outbound.get_mut(field) {
+                    rename_value(value, renames);
+                }
+            }
+            if let Some(members) = outbound.get_mut("outbounds").and_then(Value::as_array_mut) {
+                members
+                    .iter_mut()
+                    .for_each(|member| rename_value(member, renames));
+            }
+        }
+    }
+    if let Some(endpoints) = data.get_mut("endpoints").and_then(Value::as_array_mut) {
+        for detour in endpoints.iter_mut().filter_map(|e| e.get_mut("detour")) {
+            rename_value(detour, renames);
+        }
+    }
+    if let Some(route) = data.get_mut("route") {
+        if let Some(fallback) = route.get_mut("final") {
+            rename_value(fallback, renames);
+        }
+        if let Some(rules) = route.get_mut("rules").and_then(Value::as_array_mut) {
+            rename_rules(rules, renames);
+        }
+        if let Some(rule_sets) = route.get_mut("rule_set").and_then(Value::as_array_mut) {
+            for detour in rule_sets
+                .iter_mut()
+                .filter_map(|rule_set| rule_set.get_mut("download_detour"))
+            {
+                rename_value(detour, renames);
+            }
+        }
+    }
+    if let Some(dns) = data.get_mut("dns") {
+        if let Some(servers) = dns.get_mut("servers").and_then(Value::as_array_mut) {
+            for detour in servers
+                .iter_mut()
+                .filter_map(|server| server.get_mut("detour"))
+            {
+                rename_value(detour, renames);
+            }
+        }
+        if let Some(rules) = dns.get_mut("rules").and_then(Value::as_array_mut) {
+            rename_rules(rules, renames);
+        }
+    }
+}
+
+/// Rename the `outbound` of route or DNS rules, descending into logical rules.
+fn rename_rules(rules: &mut [Value], renames: &HashMap<String, String>) {
+    for rule in rules {
+        if let Some(outbound) = rule.get_mut("outbound") {
+            rename_value(outbound, renames);
+        }
+        if let Some(rules) = rule.get_mut("rules").and_then(Value::as_array_mut) {
+            rename_rules(rules, renames);
+        }
+    }
+}
+
+/// Remove outbounds and drop them from groups, removing groups left empty.
+///
+/// Returns every removed tag, including emptied groups.
+pub fn remove_outbounds(data: &mut Config, tags: &[String]) -> Vec<String> {
+    let Some(outbounds) = outbounds_mut(data) else {
+        return Vec::new();
+    };
+    let mut removed: Vec<String> = Vec::new();
+    let mut pending: Vec<String> = tags.to_vec();
+    // Emptying a group can empty the groups containing it in turn.
+    while !pending.is_empty() {
+        outbounds.retain(|outbound| {
+            let tag = outbound.get("tag").and_then(Value::as_str);
+            !tag.is_some_and(|tag| pending.iter().any(|pending| pending == tag))
+        });
+        removed.append(&mut pending);
+
+        for outbound in outbounds.iter_mut() {
+            let Some(members) = outbound.get_mut("outbounds").and_then(Value::as_array_mut) else {
+                continue;
+            };
+            members.retain(|member| {
+                !member
+                    .as_str()
+                    .is_some_and(|member| removed.iter().any(|tag| tag == member))
+            });
+            let empty = members.is_empty();
+            if outbound
+                .get("default")
+                .and_then(Value::as_str)
+                .is_some_and(|default| removed.iter().any(|tag| tag == default))
+            {
+                if let Some(group) = outbound.as_object_mut() {
+                    group.remove("default");
+                }
+            }
+            if empty {
+                if let Some(tag) = outbound.get("tag").and_then(Value::as_str) {
+                    pending.push(tag.to_string());
+                }
+            }
+        }
+    }
+
+    let is_removed = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_str)
+            .is_some_and(|tag| removed.iter().any(|removed| removed == tag))
+    };
+    for outbound in outbounds.iter_mut() {
+        if is_removed(outbound.get("detour")) {
+            if let Some(outbound) = outbound.as_object_mut() {
+                outbound.remove("detour");
+            }
+        }
+    }
+    // Rules routing to a removed outbound go with it, `final` falls back to the first outbound.
+    if let Some(route) = data.get_mut("route").and_then(Value::as_object_mut) {
+        if is_removed(route.get("final")) {
+            route.remove("final");
+        }
+        if let Some(rules) = route.get_mut("rules").and_then(Value::as_array_mut) {
+            rules.retain(|rule| !is_removed(rule.get("outbound")));
+        }
+    }
+    removed
+}
+
+/// Remove nodes whose type isn't listed, returning the removed tags.
+pub fn keep_protocols(data: &mut Config, protocols: &[String]) -> Vec<String> {
+    let Some(outbounds) = outbounds_mut(data) else {
+        return Vec::new();
+    };
+    let dropped: Vec<String> = outbounds
+        .iter()
+        .filter_map(Node::from_outbound)
+        .filter(|node| !protocols.contains(&node.protocol))
+        .map(|node| node.tag)
+        .collect();
+    remove_outbounds(data, &dropped)
+}
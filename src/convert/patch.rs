@@ -0,0 +1,141 @@
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+pub fn load_patch(path: &Path) -> Result<Vec<Value>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Io(format!("Can't read patch {}: {e}", path.display())))?;
+    let operations: Vec<Value> = serde_json::from_str(&content)
+        .map_err(|e| Error::Parse(format!("Can't parse patch {}: {e}", path.display())))?;
+    Ok(operations)
+}
+
+fn split_pointer(path: &str) -> std::result::Result<(&str, String), String> {
+    let (parent, token) = path
+        .rsplit_once('/')
+        .ok_or_else(|| format!("Invalid JSON pointer: {path}"))?;
+    Ok((parent, token.replace("~1", "/").replace("~0", "~")))
+}
+
+fn patch_add(doc: &mut Value, path: &str, value: Value) -> std::result::Result<(), String> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, value);
+        }
+        Some(Value::Array(array)) if token == "-" => array.push(value),
+        Some(Value::Array(array)) => match token.parse::<usize>() {
+            Ok(index) if index <= array.len() => array.insert(index, value),
+            _ => return Err(format!("Invalid array index in path: {path}")),
+        },
+        _ => return Err(format!("Path not found: {path}")),
+    }
+    Ok(())
+}
+
+fn patch_remove(doc: &mut Value, path: &str) -> std::result::Result<Value, String> {
+    let (parent, token) = split_pointer(path)?;
+    let removed = match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&token),
+        Some(Value::Array(array)) => match token.parse::<usize>() {
+            Ok(index) if index < array.len() => Some(array.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("Path not found: {path}"))
+}
+
+/// Apply RFC 6902 JSON patch operations to `doc`.
+pub fn apply_patch(doc: &mut Value, operations: &[Value]) -> Result<()> {
+    apply_operations(doc, operations).map_err(Error::Validation)
+}
+
+fn apply_operations(doc: &mut Value, operations: &[Value]) -> std::result::Result<(), String> {
+    for operation in operations {
+        let op = operation.get("op").and_then(Value::as_str);
+        let path = operation
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("Patch operation without path: {operation}"))?;
+        let from = operation.get("from").and_then(Value::as_str);
+        let value = operation.get("value").cloned();
+
+        match (op, from, value) {
+            (Some("add"), _, Some(value)) => patch_add(doc, path, value)?,
+            (Some("remove"), _, _) => {
+                patch_remove(doc, path)?;
+            }
+            (Some("replace"), _, Some(value)) => {
+                let target = doc
+                    .pointer_mut(path)
+                    .ok_or_else(|| format!("Path not found: {path}"))?;
+                *target = value;
+            }
+            (Some("move"), Some(from), _) => {
+                let value = patch_remove(doc, from)?;
+                patch_add(doc, path, value)?;
+            }
+            (Some("copy"), Some(from), _) => {
+                let value = doc
+                    .pointer(from)
+                    .cloned()
+                    .ok_or_else(|| format!("Path not found: {from}"))?;
+                patch_add(doc, path, value)?;
+            }
+            (Some("test"), _, Some(value)) => {
+                if doc.pointer(path) != Some(&value) {
+                    return Err(format!("Patch test failed at: {path}"));
+                }
+            }
+            _ => return Err(format!("Unsupported patch operation: {operation}")),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::apply_patch;
+
+    #[test]
+    fn add_appends_with_dash() {
+        let mut doc = json!({ "outbounds": [{ "tag": "a" }] });
+        let patch = [json!({ "op": "add", "path": "/outbounds/-", "value": { "tag": "b" } })];
+        apply_patch(&mut doc, &patch).unwrap();
+        assert_eq!(
+            doc,
+            json!({ "outbounds": [{ "tag": "a" }, { "tag": "b" }] })
+        );
+    }
+
+    #[test]
+    fn move_and_copy() {
+        let mut doc = json!({ "a": 1, "list": [2, 3] });
+        let patch = [
+            json!({ "op": "move", "from": "/a", "path": "/b" }),
+            json!({ "op": "copy", "from": "/list/1", "path": "/list/0" }),
+        ];
+        apply_patch(&mut doc, &patch).unwrap();
+        assert_eq!(doc, json!({ "b": 1, "list": [3, 2, 3] }));
+    }
+
+    #[test]
+    fn failing_test_op_is_an_error() {
+        let mut doc = json!({ "log": { "level": "info" } });
+        let patch = [
+            json!({ "op": "test", "path": "/log/level", "value": "debug" }),
+            json!({ "op": "remove", "path": "/log" }),
+        ];
+        assert!(apply_patch(&mut doc, &patch).is_err());
+        assert_eq!(doc, json!({ "log": { "level": "info" } }));
+    }
+}
@@ -0,0 +1,107 @@
+use std::net::TcpListener;
+
+use serde_json::Value;
+
+use crate::{Config, Result};
+
+/// An IPv6 address without the brackets of its url form, other hosts unchanged.
+pub fn bare_host(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// `host:port`, bracketing IPv6 addresses.
+pub fn socket_address(host: &str, port: impl std::fmt::Display) -> String {
+    let host = bare_host(host);
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+fn mixed_inbound(data: &Config) -> Option<&Value> {
+    data.get("inbounds")?
+        .as_array()?
+        .iter()
+        .find(|inbound| inbound.get("type").and_then(Value::as_str) == Some("mixed"))
+}
+
+/// Listen address and port of the mixed inbound.
+pub fn inbound_port(data: &Config) -> Option<(String, u16)> {
+    let inbound = mixed_inbound(data)?;
+    let listen = inbound
+        .get("listen")
+        .and_then(Value::as_str)
+        .unwrap_or("127.0.0.1");
+    let port = u16::try_from(inbound.get("listen_port")?.as_u64()?).ok()?;
+    Some((bare_host(listen).to_string(), port))
+}
+
+fn clash_api_port(data: &Config) -> Option<u16> {
+    data.get("experimental")?
+        .get("clash_api")?
+        .get("external_controller")?
+        .as_str()?
+        .rsplit_once(':')?
+        .1
+        .parse()
+        .ok()
+}
+
+pub(crate) fn bindable(listen: &str, port: u16) -> bool {
+    TcpListener::bind((listen, port)).is_ok()
+}
+
+/// Why sing-box couldn't listen on the mixed inbound port, if it couldn't.
+///
+/// `owned` is the port of the config being replaced, held by our own sing-box.
+pub fn port_conflict(data: &Config, owned: Option<u16>) -> Option<String> {
+    let (listen, port) = inbound_port(data)?;
+    if clash_api_port(data) == Some(port) {
+        return Some(format!(
+            "Mixed inbound port {port} is also the clash API port."
+        ));
+    }
+    if owned != Some(port) && !bindable(&listen, port) {
+        return Some(format!(
+            "Mixed inbound port {} is already in use.",
+            socket_address(&listen, port)
+        ));
+    }
+    None
+}
+
+/// Move the mixed inbound to `owned` or else a free port, returning it.
+///
+/// Reusing the port of the previous config keeps it stable across runs.
+pub fn move_inbound_port(data: &mut Config, owned: Option<u16>) -> Result<u16> {
+    let Some((listen, _)) = inbound_port(data) else {
+        return Ok(0);
+    };
+    let clash_api = clash_api_port(data);
+    let port = match owned.filter(|owned| Some(*owned) != clash_api) {
+        Some(owned) => owned,
+        None => loop {
+            let port = TcpListener::bind((listen.as_str(), 0))?
+                .local_addr()?
+                .port();
+            if Some(port) != clash_api {
+                break port;
+            }
+        },
+    };
+    if let Some(inbound) = data
+        .get_mut("inbounds")
+        .and_then(Value::as_array_mut)
+        .and_then(|inbounds| {
+            inbounds
+                .iter_mut()
+                .find(|inbound| inbound.get("type").and_then(Value::as_str) == Some("mixed"))
+        })
+    {
+        inbound["listen_port"] = Value::from(port);
+    }
+    Ok(port)
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+use super::Node;
+use crate::{Config, Error, Result};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RenameFile {
+    exact: HashMap<String, String>,
+    regex: Vec<RegexRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegexRule {
+    pattern: String,
+    replace: String,
+}
+
+/// Tag replacements read with `--rename-map`.
+///
+/// ```toml
+/// [exact]
+/// "HK 01" = "Hong Kong 1"
+///
+/// [[regex]]
+/// pattern = '^\[VIP\]\s*'
+/// replace = ""
+/// ```
+///
+/// An exact match wins, otherwise every regex is applied in file order,
+/// `$1` style captures included.
+#[derive(Debug, Clone, Default)]
+pub struct RenameMap {
+    exact: HashMap<String, String>,
+    regex: Vec<(Regex, String)>,
+}
+
+impl RenameMap {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::Io(format!("Can't read rename map {}: {e}", path.display())))?;
+        let file: RenameFile = toml::from_str(&content)
+            .map_err(|e| Error::Parse(format!("Can't parse rename map {}: {e}", path.display())))?;
+        let regex = file
+            .regex
+            .into_iter()
+            .map(|rule| {
+                let pattern = Regex::new(&rule.pattern).map_err(|e| {
+                    Error::Parse(format!("Invalid rename pattern {}: {e}", rule.pattern))
+                })?;
+                Ok((pattern, rule.replace))
+            })
+            .collect::<Result<_>>()?;
+        Ok(RenameMap {
+            exact: file.exact,
+            regex,
+        })
+    }
+
+    fn rename(&self, tag: &str) -> String {
+        if let Some(renamed) = self.exact.get(tag) {
+            return renamed.clone();
+        }
+        self.regex
+            .iter()
+            .fold(tag.to_string(), |tag, (pattern, replace)| {
+                pattern
+                    .replace_all(&tag, replace.as_str())
+                    .trim()
+                    .to_string()
+            })
+    }
+
+    /// Renames for the nodes of `data`, by old tag.
+    ///
+    /// Names that come out empty or collide with another outbound keep the original
+    /// tag, so group membership never becomes ambiguous.
+    pub fn renames(&self, data: &Config) -> HashMap<String, String> {
+        let outbounds: &[Value] = data
+            .get("outbounds")
+            .and_then(Value::as_array)
+            .map_or(&[], Vec::as_slice);
+        let candidates: Vec<(String, String)> = outbounds
+            .iter()
+            .filter_map(Node::from_outbound)
+            .map(|node| {
+                let renamed = self.rename(&node.tag);
+                (node.tag, renamed)
+            })
+            .collect();
+        // Groups and nodes keeping their tag claim it first.
+        let mut taken: HashSet<String> = outbounds
+            .iter()
+            .filter(|outbound| Node::from_outbound(outbound).is_none())
+            .filter_map(|outbound| outbound.get("tag")?.as_str().map(String::from))
+            .chain(
+                candidates
+                    .iter()
+                    .filter(|(tag, renamed)| tag == renamed)
+                    .map(|(tag, _)| tag.clone()),
+            )
+            .collect();
+        let mut renames = HashMap::new();
+        for (tag, renamed) in candidates {
+            if tag == renamed {
+                continue;
+            }
+            if renamed.is_empty() || !taken.insert(renamed.clone()) {
+                warn!("✖ Can't rename {tag} to {renamed:?}, keeping it.");
+                taken.insert(tag);
+                continue;
+            }
+            renames.insert(tag, renamed);
+        }
+        renames
+    }
+}
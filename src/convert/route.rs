@@ -0,0 +1,151 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+    migrate::{geo_rule_set, Version},
+    node::is_node,
+};
+use crate::Config;
+
+#[derive(Clone, Copy, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoutePreset {
+    /// China and LAN direct, everything else proxied.
+    Cn,
+    /// Only LAN direct.
+    Global,
+    /// Only GFW-listed domains proxied.
+    GfwOnly,
+}
+
+/// Where proxied traffic goes, the first group or else the first node.
+fn proxy_tag(outbounds: &[Value]) -> Option<String> {
+    let tag = |outbound: &Value| outbound.get("tag")?.as_str().map(String::from);
+    outbounds
+        .iter()
+        .find(|outbound| {
+            matches!(
+                outbound.get("type").and_then(Value::as_str),
+                Some("selector" | "urltest")
+            )
+        })
+        .and_then(tag)
+        .or_else(|| outbounds.iter().filter(|o| is_node(o)).find_map(tag))
+}
+
+/// Domain and IP rules, as rule-sets from sing-box 1.8 on and geosite/geoip fields before.
+struct Rules {
+    rule_sets: bool,
+    list: Vec<Value>,
+    rule_set_list: Vec<Value>,
+}
+
+impl Rules {
+    fn push(&mut self, geosite: &[&str], geoip: &[&str], mut action: Value) {
+        if self.rule_sets {
+            let mut names = Vec::new();
+            for (kind, codes) in [("geosite", geosite), ("geoip", geoip)] {
+                for code in codes {
+                    self.rule_set_list.push(geo_rule_set(kind, code));
+                    names.push(format!("{kind}-{code}"));
+                }
+            }
+            action["rule_set"] = json!(names);
+            self.list.push(action);
+            return;
+        }
+        // geosite and geoip in one legacy rule must both match, split them.
+        for (kind, codes) in [("geosite", geosite), ("geoip", geoip)] {
+            if !codes.is_empty() {
+                let mut rule = action.clone();
+                rule[kind] = json!(codes);
+                self.list.push(rule);
+            }
+        }
+    }
+}
+
+/// Whether `version` understands rule actions, an unknown version is assumed recent.
+pub(super) fn has_rule_actions(version: Option<Version>) -> bool {
+    version.is_none_or(|version| version >= Version(1, 11, 0))
+}
+
+/// Replace the route section with a curated preset, adding a `direct` outbound if missing.
+///
+/// Rules are written for `version`: rule actions from sing-box 1.11 on, and the `block`
+/// outbound before; rule-sets from 1.8 on, and geosite/geoip before. Older cores sniff
+/// on the inbound instead, see [`enable_inbound_sniffing`].
+pub fn apply_route_preset(
+    data: &mut Config,
+    preset: RoutePreset,
+    block_ads: bool,
+    version: Option<Version>,
+) {
+    let Some(outbounds) = data.get_mut("outbounds").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let Some(proxy) = proxy_tag(outbounds) else {
+        return;
+    };
+    if !outbounds.iter().any(|outbound| outbound["tag"] == "direct") {
+        outbounds.push(json!({ "type": "direct", "tag": "direct" }));
+    }
+    let actions = has_rule_actions(version);
+    if block_ads && !actions && !outbounds.iter().any(|outbound| outbound["tag"] == "block") {
+        outbounds.push(json!({ "type": "block", "tag": "block" }));
+    }
+
+    let mut rules = Rules {
+        rule_sets: version.is_none_or(|version| version >= Version(1, 8, 0)),
+        list: Vec::new(),
+        rule_set_list: Vec::new(),
+    };
+    // Domain rules only match once the destination domain is sniffed.
+    if actions {
+        rules.list.push(json!({ "action": "sniff" }));
+    }
+    if block_ads {
+        let reject = if actions {
+            json!({ "action": "reject" })
+        } else {
+            json!({ "outbound": "block" })
+        };
+        rules.push(&["category-ads-all"], &[], reject);
+    }
+    let private = if rules.rule_sets {
+        json!({ "ip_is_private": true, "outbound": "direct" })
+    } else {
+        json!({ "geoip": ["private"], "outbound": "direct" })
+    };
+    rules.list.push(private);
+    let fallback = match preset {
+        RoutePreset::Cn => {
+            rules.push(&["cn"], &["cn"], json!({ "outbound": "direct" }));
+            proxy.as_str()
+        }
+        RoutePreset::Global => proxy.as_str(),
+        RoutePreset::GfwOnly => {
+            rules.push(&["gfw"], &[], json!({ "outbound": proxy }));
+            "direct"
+        }
+    };
+    let mut route = json!({
+        "rules": rules.list,
+        "final": fallback,
+    });
+    if rules.rule_sets {
+        route["rule_set"] = json!(rules.rule_set_list);
+    }
+    data.insert(String::from("route"), route);
+}
+
+/// Turn on sniffing on every inbound, what domain rules need before sing-box 1.11.
+pub fn enable_inbound_sniffing(data: &mut Config) {
+    let inbounds = data.get_mut("inbounds").and_then(Value::as_array_mut);
+    for inbound in inbounds.into_iter().flatten() {
+        if let Some(inbound) = inbound.as_object_mut() {
+            inbound.insert(String::from("sniff"), json!(true));
+        }
+    }
+}
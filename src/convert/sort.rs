@@ -0,0 +1,75 @@
+use std::{collections::HashMap, time::Duration};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::node::{node_region, Node};
+use crate::Config;
+
+#[derive(Clone, Copy, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    Region,
+    /// Fastest first, needs measured latencies.
+    Latency,
+    Protocol,
+}
+
+/// Sort nodes in place of each other and the members of every group to match.
+///
+/// Nodes without a region or latency go last, ties are broken by tag.
+pub fn sort_outbounds(
+    data: &mut Config,
+    key: SortKey,
+    countries: &HashMap<String, String>,
+    latency: &HashMap<String, Duration>,
+) {
+    let Some(outbounds) = data.get_mut("outbounds").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let slots: Vec<usize> = (0..outbounds.len())
+        .filter(|&index| Node::from_outbound(&outbounds[index]).is_some())
+        .collect();
+    let mut nodes: Vec<Node> = slots
+        .iter()
+        .filter_map(|&index| Node::from_outbound(&outbounds[index]))
+        .collect();
+
+    let region = |node: &Node| {
+        countries
+            .get(&node.tag)
+            .map(String::as_str)
+            .or_else(|| node_region(&node.tag))
+    };
+    match key {
+        SortKey::Name => nodes.sort_by(|a, b| a.tag.cmp(&b.tag)),
+        SortKey::Region => nodes.sort_by(|a, b| {
+            let (a_region, b_region) = (region(a), region(b));
+            (a_region.is_none(), a_region, &a.tag).cmp(&(b_region.is_none(), b_region, &b.tag))
+        }),
+        SortKey::Latency => nodes.sort_by(|a, b| {
+            let (a_latency, b_latency) = (latency.get(&a.tag), latency.get(&b.tag));
+            (a_latency.is_none(), a_latency, &a.tag).cmp(&(b_latency.is_none(), b_latency, &b.tag))
+        }),
+        SortKey::Protocol => {
+            nodes.sort_by(|a, b| (&a.protocol, &a.tag).cmp(&(&b.protocol, &b.tag)))
+        }
+    }
+
+    let rank: HashMap<String, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(rank, node)| (node.tag.clone(), rank))
+        .collect();
+    for (slot, node) in slots.into_iter().zip(nodes) {
+        outbounds[slot] = node.outbound;
+    }
+    // Groups and special outbounds keep their place ahead of the nodes.
+    for outbound in outbounds.iter_mut() {
+        if let Some(members) = outbound.get_mut("outbounds").and_then(Value::as_array_mut) {
+            members.sort_by_key(|member| member.as_str().and_then(|tag| rank.get(tag)).copied());
+        }
+    }
+}
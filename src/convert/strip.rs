@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::{Config, Error, Result};
+
+/// Top-level sections of a sing-box config `--strip` may remove.
+pub const STRIPPABLE: [&str; 8] = [
+    "log",
+    "dns",
+    "ntp",
+    "certificate",
+    "endpoints",
+    "route",
+    "experimental",
+    "services",
+];
+
+fn tags<'a>(data: &'a Config, section: &str, list: &str) -> HashSet<&'a str> {
+    let values = match section {
+        "" => data.get(list),
+        _ => data.get(section).and_then(|section| section.get(list)),
+    };
+    values
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.get("tag")?.as_str())
+        .collect()
+}
+
+fn rules<'a>(data: &'a Config, section: &str) -> impl Iterator<Item = (usize, &'a Value)> {
+    data.get(section)
+        .and_then(|section| section.get("rules"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .enumerate()
+}
+
+/// Rule-set names referenced by a rule, a single string or a list.
+fn rule_sets(rule: &Value) -> Vec<&str> {
+    match rule.get("rule_set") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// DNS server a `domain_resolver` points at, written as a tag or `{ "server": tag }`.
+fn resolver(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| value.get("server")?.as_str())
+}
+
+/// References to rule-sets, DNS servers and endpoints that no longer exist.
+fn dangling_references(data: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let rule_set_tags = tags(data, "route", "rule_set");
+    for section in ["route", "dns"] {
+        for (index, rule) in rules(data, section) {
+            for name in rule_sets(rule) {
+                if !rule_set_tags.contains(name) {
+                    problems.push(format!("{section}.rules[{index}]: unknown rule-set {name}"));
+                }
+            }
+        }
+    }
+
+    let servers = tags(data, "dns", "servers");
+    let outbounds = data.get("outbounds").and_then(Value::as_array);
+    for (index, outbound) in outbounds.into_iter().flatten().enumerate() {
+        if let Some(server) = outbound.get("domain_resolver").and_then(resolver) {
+            if !servers.contains(server) {
+                problems.push(format!("outbounds[{index}]: unknown dns server {server}"));
+            }
+        }
+    }
+    if let Some(server) = data
+        .get("route")
+        .and_then(|route| route.get("default_domain_resolver"))
+        .and_then(resolver)
+    {
+        if !servers.contains(server) {
+            problems.push(format!(
+                "route.default_domain_resolver: unknown dns server {server}"
+            ));
+        }
+    }
+
+    // Outbounds may detour through an endpoint.
+    let mut detours = tags(data, "", "outbounds");
+    detours.extend(tags(data, "", "endpoints"));
+    for (index, outbound) in outbounds.into_iter().flatten().enumerate() {
+        if let Some(detour) = outbound.get("detour").and_then(Value::as_str) {
+            if !detours.contains(detour) {
+                problems.push(format!("outbounds[{index}]: unknown detour {detour}"));
+            }
+        }
+    }
+    problems
+}
+
+/// Remove top-level sections, failing when what is left refers into them.
+pub fn strip_sections(data: &mut Config, sections: &[String]) -> Result<()> {
+    let before = dangling_references(data);
+    for section in sections {
+        if !STRIPPABLE.contains(&section.as_str()) {
+            return Err(Error::Validation(format!(
+                "Can't strip {section}, expected one of: {}",
+                STRIPPABLE.join(", ")
+            )));
+        }
+        data.remove(section);
+    }
+    let problems: Vec<String> = dangling_references(data)
+        .into_iter()
+        .filter(|problem| !before.contains(problem))
+        .collect();
+    if !problems.is_empty() {
+        return Err(Error::Validation(format!(
+            "Stripping {} leaves dangling references:\n    {}",
+            sections.join(", "),
+            problems.join("\n    ")
+        )));
+    }
+    Ok(())
+}
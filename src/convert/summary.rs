@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{inbound_port, node_region, socket_address, Node};
+use crate::Config;
+
+/// A node removed during conversion and why.
+#[derive(Debug, Clone, Serialize)]
+pub struct Dropped {
+    pub tag: String,
+    pub reason: String,
+}
+
+impl Dropped {
+    pub fn new(tag: impl Into<String>, reason: impl Into<String>) -> Self {
+        Dropped {
+            tag: tag.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// What a conversion produced, to spot a provider quietly changing its lineup.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub nodes: usize,
+    /// Node count by outbound type.
+    pub protocols: BTreeMap<String, usize>,
+    /// Node count by region guessed from the tag, `other` when none matches.
+    pub regions: BTreeMap<String, usize>,
+    pub dropped: Vec<Dropped>,
+    /// Tag and address of the mixed inbound behind the external proxy.
+    pub inbound: Option<String>,
+    /// Size of the written config in bytes.
+    pub size: u64,
+}
+
+impl Summary {
+    pub fn new(data: &Config, dropped: Vec<Dropped>) -> Self {
+        let mut summary = Summary {
+            dropped,
+            ..Summary::default()
+        };
+        for node in data
+            .get("outbounds")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Node::from_outbound)
+        {
+            summary.nodes += 1;
+            *summary.protocols.entry(node.protocol).or_default() += 1;
+            let region = node_region(&node.tag).unwrap_or("other");
+            *summary.regions.entry(region.to_string()).or_default() += 1;
+        }
+        summary.inbound = inbound_port(data).map(|(listen, port)| {
+            let tag = data
+                .get("inbounds")
+                .and_then(Value::as_array)
+                .and_then(|inbounds| {
+                    inbounds
+                        .iter()
+                        .find(|inbound| inbound["type"] == "mixed")?
+                        .get("tag")?
+                        .as_str()
+                })
+                .unwrap_or("mixed");
+            format!("{tag} {}", socket_address(&listen, port))
+        });
+        summary
+    }
+}
@@ -0,0 +1,106 @@
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use crate::{Config, Error, Result};
+
+pub fn load_template(path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Io(format!("Can't read template {}: {e}", path.display())))?;
+    let template: Config = serde_json::from_str(&content)
+        .map_err(|e| Error::Parse(format!("Can't parse template {}: {e}", path.display())))?;
+    Ok(template)
+}
+
+/// Read the overrides merged into every generated config, `None` when the file doesn't exist.
+pub fn load_overrides(path: &Path) -> Result<Option<Config>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Io(format!("Can't read overrides {}: {e}", path.display())))?;
+    let overrides: Config = serde_json::from_str(&content)
+        .map_err(|e| Error::Parse(format!("Can't parse overrides {}: {e}", path.display())))?;
+    Ok(Some(overrides))
+}
+
+/// Merge `overlay` into `target`, objects recursively and anything else replaced.
+///
+/// With `by_tag`, arrays whose overlay entries all carry a tag merge entry by entry
+/// instead, entries with a new tag being appended.
+pub fn deep_merge(target: &mut Value, overlay: Value, by_tag: bool) {
+    match (target, overlay) {
+        (Value::Object(target), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match target.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value, by_tag),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(overlay))
+            if by_tag && overlay.iter().all(|item| item.get("tag").is_some()) =>
+        {
+            for item in overlay {
+                match target
+                    .iter_mut()
+                    .find(|existing| existing.get("tag") == item.get("tag"))
+                {
+                    Some(existing) => deep_merge(existing, item, by_tag),
+                    None => target.push(item),
+                }
+            }
+        }
+        (target, overlay) => *target = overlay,
+    }
+}
+
+/// Layer `top` over `base`, entries in `base` sharing a tag with `top` are dropped.
+fn layer_tagged(base: Value, top: Value) -> Value {
+    match (base, top) {
+        (Value::Array(base), Value::Array(mut top)) => {
+            let tags: Vec<Value> = top.iter().filter_map(|v| v.get("tag").cloned()).collect();
+            for item in base {
+                if item.get("tag").is_none_or(|tag| !tags.contains(tag)) {
+                    top.push(item);
+                }
+            }
+            Value::Array(top)
+        }
+        (_, top) => top,
+    }
+}
+
+/// Merge local overrides into a generated config, the overrides winning.
+///
+/// Objects merge recursively, arrays of tagged entries merge by tag and any other
+/// array is replaced as a whole.
+pub fn apply_overrides(data: &mut Config, overrides: Config) {
+    for (key, value) in overrides {
+        match data.get_mut(&key) {
+            Some(existing) => deep_merge(existing, value, true),
+            None => {
+                data.insert(key, value);
+            }
+        }
+    }
+}
+
+pub fn apply_template(mut data: Config, template: Config) -> Config {
+    for (key, value) in template {
+        let merged = match data.remove(&key) {
+            Some(existing) if key == "outbounds" || key == "inbounds" => {
+                layer_tagged(value, existing)
+            }
+            Some(mut existing) => {
+                deep_merge(&mut existing, value, false);
+                existing
+            }
+            None => value,
+        };
+        data.insert(key, merged);
+    }
+    data
+}
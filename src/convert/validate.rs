@@ -0,0 +1,134 @@
+use serde_json::Value;
+
+use crate::Config;
+
+const SERVER_TYPES: [&str; 13] = [
+    "socks",
+    "http",
+    "shadowsocks",
+    "vmess",
+    "vless",
+    "trojan",
+    "hysteria",
+    "hysteria2",
+    "tuic",
+    "shadowtls",
+    "anytls",
+    "ssh",
+    "naive",
+];
+
+fn validate_port(value: Option<&Value>, context: &str, problems: &mut Vec<String>) {
+    match value {
+        Some(port) if port.as_u64().is_some_and(|p| (1..=65535).contains(&p)) => {}
+        Some(port) => problems.push(format!("{context}: invalid port {port}")),
+        None => problems.push(format!("{context}: missing port")),
+    }
+}
+
+/// Structural checks catching obvious problems without a local sing-box.
+pub fn validate_config(data: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Some(inbounds) = data.get("inbounds") {
+        match inbounds.as_array() {
+            Some(inbounds) => {
+                for (index, inbound) in inbounds.iter().enumerate() {
+                    let context = format!("inbounds[{index}]");
+                    if inbound.get("type").and_then(Value::as_str).is_none() {
+                        problems.push(format!("{context}: missing type"));
+                    }
+                    if let Some(port) = inbound.get("listen_port") {
+                        validate_port(Some(port), &context, &mut problems);
+                    }
+                }
+            }
+            None => problems.push(String::from("inbounds: not an array")),
+        }
+    }
+
+    let mut tags = Vec::new();
+    let outbounds = match data.get("outbounds").and_then(Value::as_array) {
+        Some(outbounds) if !outbounds.is_empty() => outbounds.as_slice(),
+        Some(_) => {
+            problems.push(String::from("outbounds: empty"));
+            &[]
+        }
+        None => {
+            problems.push(String::from("outbounds: missing or not an array"));
+            &[]
+        }
+    };
+    for (index, outbound) in outbounds.iter().enumerate() {
+        let context = format!("outbounds[{index}]");
+        let Some(outbound_type) = outbound.get("type").and_then(Value::as_str) else {
+            problems.push(format!("{context}: missing type"));
+            continue;
+        };
+        match outbound.get("tag").and_then(Value::as_str) {
+            Some(tag) if tags.contains(&tag) => {
+                problems.push(format!("{context}: duplicate tag {tag}"))
+            }
+            Some(tag) => tags.push(tag),
+            None => problems.push(format!("{context}: missing tag")),
+        }
+        if SERVER_TYPES.contains(&outbound_type) {
+            if outbound.get("server").and_then(Value::as_str).is_none() {
+                problems.push(format!("{context}: missing server"));
+            }
+            // Port hopping outbounds (hysteria2) may give `server_ports` instead.
+            if outbound.get("server_ports").is_none() {
+                validate_port(outbound.get("server_port"), &context, &mut problems);
+            }
+        }
+    }
+
+    // Groups and routes may point at endpoints (sing-box 1.11), e.g. wireguard.
+    let endpoints = data.get("endpoints").and_then(Value::as_array);
+    for (index, endpoint) in endpoints.into_iter().flatten().enumerate() {
+        match endpoint.get("tag").and_then(Value::as_str) {
+            Some(tag) if tags.contains(&tag) => {
+                problems.push(format!("endpoints[{index}]: duplicate tag {tag}"))
+            }
+            Some(tag) => tags.push(tag),
+            None => problems.push(format!("endpoints[{index}]: missing tag")),
+        }
+    }
+
+    for (index, outbound) in outbounds.iter().enumerate() {
+        let Some(members) = outbound.get("outbounds").and_then(Value::as_array) else {
+            continue;
+        };
+        if members.is_empty() {
+            problems.push(format!("outbounds[{index}]: empty group"));
+        }
+        for member in members {
+            if !member.as_str().is_some_and(|m| tags.contains(&m)) {
+                problems.push(format!("outbounds[{index}]: unknown member {member}"));
+            }
+        }
+    }
+
+    if let Some(route) = data.get("route") {
+        if let Some(outbound) = route.get("final").and_then(Value::as_str) {
+            if !tags.contains(&outbound) {
+                problems.push(format!("route.final: unknown outbound {outbound}"));
+            }
+        }
+        for (index, rule) in route
+            .get("rules")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            if let Some(outbound) = rule.get("outbound").and_then(Value::as_str) {
+                if !tags.contains(&outbound) {
+                    problems.push(format!("route.rules[{index}]: unknown outbound {outbound}"));
+                }
+            }
+        }
+    }
+
+    problems
+}
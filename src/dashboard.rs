@@ -0,0 +1,253 @@
+use std::{
+    io::IsTerminal,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{convert::ClashApi, source::format_bytes, Error, Result};
+
+const REFRESH: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connections {
+    #[serde(default)]
+    download_total: u64,
+    #[serde(default)]
+    upload_total: u64,
+    #[serde(default)]
+    connections: Vec<Connection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Connection {
+    #[serde(default)]
+    metadata: Value,
+    #[serde(default)]
+    upload: u64,
+    #[serde(default)]
+    download: u64,
+    #[serde(default)]
+    chains: Vec<String>,
+    #[serde(default)]
+    rule: String,
+}
+
+impl Connection {
+    fn destination(&self) -> String {
+        let field = |name| {
+            self.metadata
+                .get(name)
+                .and_then(Value::as_str)
+                .unwrap_or("")
+        };
+        let host = match field("host") {
+            "" => field("destinationIP"),
+            host => host,
+        };
+        format!("{host}:{}", field("destinationPort"))
+    }
+}
+
+/// What the dashboard shows, refreshed every second.
+#[derive(Default)]
+struct Snapshot {
+    connections: Connections,
+    /// Selector tag and its current choice.
+    selectors: Vec<(String, String)>,
+    upload_rate: u64,
+    download_rate: u64,
+    error: Option<String>,
+}
+
+struct Dashboard {
+    client: Client,
+    base: String,
+    secret: String,
+    snapshot: Snapshot,
+    last: Option<(Instant, u64, u64)>,
+}
+
+impl Dashboard {
+    async fn get(&self, path: &str) -> Result<Value> {
+        let mut request = self.client.get(format!("{}{path}", self.base));
+        if !self.secret.is_empty() {
+            request = request.bearer_auth(&self.secret);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Error::Network(format!(
+                "Clash API {path}: HTTP {}",
+                response.status()
+            )));
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn refresh(&mut self) {
+        let result = async {
+            let connections: Connections = serde_json::from_value(self.get("/connections").await?)?;
+            let proxies = self.get("/proxies").await?;
+            Ok::<_, Error>((connections, proxies))
+        }
+        .await;
+        let (connections, proxies) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.snapshot.error = Some(e.to_string());
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        if let Some((at, upload, download)) = self.last {
+            let secs = now.duration_since(at).as_secs_f64().max(0.001);
+            self.snapshot.upload_rate =
+                (connections.upload_total.saturating_sub(upload) as f64 / secs) as u64;
+            self.snapshot.download_rate =
+                (connections.download_total.saturating_sub(download) as f64 / secs) as u64;
+        }
+        self.last = Some((now, connections.upload_total, connections.download_total));
+
+        let mut selectors: Vec<(String, String)> = proxies
+            .get("proxies")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter(|(_, proxy)| {
+                proxy
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .is_some_and(|t| t.eq_ignore_ascii_case("selector"))
+            })
+            .filter_map(|(tag, proxy)| {
+                let now = proxy.get("now")?.as_str()?;
+                Some((tag.clone(), now.to_string()))
+            })
+            .collect();
+        selectors.sort();
+
+        self.snapshot.connections = connections;
+        self.snapshot.selectors = selectors;
+        self.snapshot.error = None;
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let snapshot = &self.snapshot;
+        let [summary_area, selectors_area, connections_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(snapshot.selectors.len().clamp(1, 8) as u16 + 2),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let summary = match &snapshot.error {
+            Some(error) => Line::from(format!("✖ {error}")),
+            None => Line::from(format!(
+                "↑ {}/s  ↓ {}/s   total ↑ {}  ↓ {}   {} connections",
+                format_bytes(snapshot.upload_rate),
+                format_bytes(snapshot.download_rate),
+                format_bytes(snapshot.connections.upload_total),
+                format_bytes(snapshot.connections.download_total),
+                snapshot.connections.connections.len(),
+            )),
+        };
+        frame.render_widget(
+            Paragraph::new(summary).block(Block::bordered().title(format!(" {} ", self.base))),
+            summary_area,
+        );
+
+        let selectors: Vec<Line> = snapshot
+            .selectors
+            .iter()
+            .map(|(tag, now)| Line::from(format!("{tag} → {now}")))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(selectors).block(Block::bordered().title(" Selectors ")),
+            selectors_area,
+        );
+
+        let mut connections: Vec<&Connection> = snapshot.connections.connections.iter().collect();
+        connections.sort_by_key(|c| std::cmp::Reverse(c.download + c.upload));
+        let rows = connections.into_iter().map(|connection| {
+            Row::new(vec![
+                connection.destination(),
+                connection.chains.join(" ← "),
+                connection.rule.clone(),
+                format_bytes(connection.upload),
+                format_bytes(connection.download),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(35),
+                Constraint::Percentage(30),
+                Constraint::Percentage(15),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+            ],
+        )
+        .header(
+            Row::new(["Destination", "Chain", "Rule", "Up", "Down"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Connections "));
+        frame.render_widget(table, connections_area);
+
+        frame.render_widget(Paragraph::new("q quit"), help_area);
+    }
+
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            self.refresh().await;
+            terminal.draw(|frame| self.draw(frame))?;
+            let deadline = Instant::now() + REFRESH;
+            while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+                if !event::poll(timeout)? {
+                    break;
+                }
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press
+                        && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Show traffic, selectors and connections of a running sing-box until `q` is pressed.
+pub async fn dashboard(clash_api: &ClashApi) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return Err(Error::Validation(String::from(
+            "The dashboard needs an interactive terminal.",
+        )));
+    }
+    let mut dashboard = Dashboard {
+        client: Client::builder().timeout(Duration::from_secs(3)).build()?,
+        base: format!("http://{}", clash_api.address),
+        secret: clash_api.secret.clone(),
+        snapshot: Snapshot::default(),
+        last: None,
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let result = dashboard.run(&mut terminal).await;
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,204 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::{
+    convert::{bindable, inbound_port},
+    install::{detect_sing_box_version, find_sing_box},
+    output::CONFIG_FILE,
+    Config,
+};
+
+/// What `doctor` looks at.
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    /// sing-box binary given with `--exec`.
+    pub exec: Option<PathBuf>,
+    pub out_dir: PathBuf,
+    pub urls: Vec<String>,
+    /// Mixed inbound port when no config has been written yet.
+    pub port: u16,
+    pub timeout: Duration,
+}
+
+/// Result of one diagnostic.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    /// What to do about a failed check.
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            ok: true,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            ok: false,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+fn check_sing_box(exec: Option<&Path>) -> Check {
+    let path = match exec {
+        Some(exec) if exec.is_file() => exec.display().to_string(),
+        Some(exec) => {
+            return Check::fail(
+                "sing-box",
+                format!("{} does not exist", exec.display()),
+                "Fix the --exec path or drop it to use sing-box from PATH.",
+            )
+        }
+        None => match find_sing_box(false) {
+            Ok(path) => path,
+            Err(_) => {
+                return Check::fail(
+                    "sing-box",
+                    "not found on PATH or in the managed bin directory",
+                    "Run the `install` subcommand, install sing-box with your package manager or pass --exec.",
+                )
+            }
+        },
+    };
+    match detect_sing_box_version(Some(Path::new(&path))) {
+        Some(version) => Check::pass("sing-box", format!("{version} at {path}")),
+        None => Check::fail(
+            "sing-box",
+            format!("{path} doesn't report a version"),
+            "Check that it is a working sing-box binary for this platform.",
+        ),
+    }
+}
+
+/// Where Surge keeps its profiles, iCloud first.
+fn surge_profile_dirs() -> Vec<PathBuf> {
+    let Some(home) = env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+    vec![
+        home.join("Library/Mobile Documents/iCloud~com~nssurge~inc/Documents"),
+        home.join("Library/Application Support/Surge/Profiles"),
+    ]
+}
+
+fn check_surge() -> Check {
+    if !cfg!(target_os = "macos") {
+        return Check::pass(
+            "Surge",
+            "not macOS, paste the [Proxy] line into Surge by hand",
+        );
+    }
+    match surge_profile_dirs().into_iter().find(|dir| dir.is_dir()) {
+        Some(dir) => Check::pass("Surge", format!("profiles in {}", dir.display())),
+        None => Check::fail(
+            "Surge",
+            "no Surge profile directory found",
+            "Install and open Surge once, or ignore this when using another client.",
+        ),
+    }
+}
+
+fn check_writable(dir: &Path) -> Check {
+    let probe = dir.join(".doctor");
+    let result = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&probe, b""))
+        .and_then(|()| fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::pass("Output directory", format!("{} is writable", dir.display())),
+        Err(e) => Check::fail(
+            "Output directory",
+            format!("can't write to {}: {e}", dir.display()),
+            "Pass a writable --out-dir or fix the directory permissions.",
+        ),
+    }
+}
+
+async fn check_host(url: &str, timeout: Duration) -> Check {
+    let Some((host, port)) = Url::parse(url)
+        .ok()
+        .and_then(|url| Some((url.host_str()?.to_string(), url.port_or_known_default()?)))
+    else {
+        return Check::fail(
+            "Subscription host",
+            "invalid url",
+            "Check the subscription url.",
+        );
+    };
+    let name = format!("Subscription host {host}");
+    match tokio::time::timeout(
+        timeout,
+        TcpStream::connect((host.trim_matches(['[', ']']), port)),
+    )
+    .await
+    {
+        Ok(Ok(_)) => Check::pass(name, format!("reachable on port {port}")),
+        Ok(Err(e)) => Check::fail(
+            name,
+            format!("can't connect to port {port}: {e}"),
+            "Check DNS and your network, or fetch through --proxy.",
+        ),
+        Err(_) => Check::fail(
+            name,
+            format!("no answer on port {port} within {}s", timeout.as_secs()),
+            "The host may be blocked, fetch through --proxy.",
+        ),
+    }
+}
+
+fn check_port(out_dir: &Path, port: u16) -> Check {
+    let existing: Option<Config> = fs::read(out_dir.join(CONFIG_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok());
+    let (listen, port) = existing
+        .as_ref()
+        .and_then(inbound_port)
+        .unwrap_or((String::from("127.0.0.1"), port));
+    if bindable(&listen, port) {
+        Check::pass("Inbound port", format!("{port} is free"))
+    } else {
+        Check::fail(
+            "Inbound port",
+            format!("{port} is in use, fine if it is the sing-box running this config"),
+            "Otherwise pass --port with a free port or --auto-port.",
+        )
+    }
+}
+
+/// Run every diagnostic, most support questions come down to one of these.
+pub async fn doctor(options: &DoctorOptions) -> Vec<Check> {
+    let mut checks = vec![
+        check_sing_box(options.exec.as_deref()),
+        check_surge(),
+        check_writable(&options.out_dir),
+    ];
+    if options.urls.is_empty() {
+        checks.push(Check::fail(
+            "Subscription host",
+            "no subscription url configured",
+            "Pass --url, set url in the settings file or add a profile.",
+        ));
+    }
+    for url in &options.urls {
+        checks.push(check_host(url, options.timeout).await);
+    }
+    checks.push(check_port(&options.out_dir, options.port));
+    checks
+}
@@ -0,0 +1,98 @@
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use age::{secrecy::ExposeSecret, x25519::Identity};
+use tracing::info;
+
+use crate::{settings::default_settings_path, Error, Result};
+
+/// First line of every age file, plain files never start with it.
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1";
+
+/// `key.txt` next to the settings file.
+pub fn default_key_path() -> PathBuf {
+    default_settings_path().with_file_name("key.txt")
+}
+
+/// Encrypts cached subscriptions and history backups with an age X25519 key.
+#[derive(Clone)]
+pub struct Encryption {
+    identity: Identity,
+}
+
+impl fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encryption")
+            .field("recipient", &self.identity.to_public().to_string())
+            .finish()
+    }
+}
+
+impl Encryption {
+    /// Read the age identity at `path`, generating one readable only by the user if missing.
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let content = fs::read_to_string(path)
+                .map_err(|e| Error::Io(format!("Can't read key {}: {e}", path.display())))?;
+            let identity = content
+                .lines()
+                .map(str::trim)
+                .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+                .and_then(|line| Identity::from_str(line).ok())
+                .ok_or_else(|| Error::Parse(format!("No age secret key in {}", path.display())))?;
+            return Ok(Encryption { identity });
+        }
+
+        let identity = Identity::generate();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        writeln!(file, "# public key: {}", identity.to_public())?;
+        writeln!(file, "{}", identity.to_string().expose_secret())?;
+        info!("✅ Generated encryption key: {}", path.display());
+        Ok(Encryption { identity })
+    }
+
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        age::encrypt(&self.identity.to_public(), plain)
+            .map_err(|e| Error::Io(format!("Can't encrypt: {e}")))
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        age::decrypt(&self.identity, data).map_err(|e| Error::Io(format!("Can't decrypt: {e}")))
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(AGE_HEADER)
+}
+
+/// Encrypt `plain` when a key is given.
+pub fn seal(encryption: Option<&Encryption>, plain: &[u8]) -> Result<Vec<u8>> {
+    match encryption {
+        Some(encryption) => encryption.encrypt(plain),
+        None => Ok(plain.to_vec()),
+    }
+}
+
+/// Decrypt `data` if it is encrypted, plain files written before encryption pass through.
+pub fn open(encryption: Option<&Encryption>, data: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    match encryption {
+        Some(encryption) => encryption.decrypt(&data),
+        None => Err(Error::Validation(String::from(
+            "File is encrypted, pass --encrypt to read it.",
+        ))),
+    }
+}
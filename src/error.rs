@@ -0,0 +1,92 @@
+use thiserror::Error;
+
+use crate::source::{redact_error, redact_url};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Url(String),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("IO error: {0}")]
+    Io(String),
+    #[error("Validation failed: {0}")]
+    Validation(String),
+    #[error("Install error: {0}")]
+    Install(String),
+    #[error("Aborted, {0} is unchanged.")]
+    Aborted(String),
+}
+
+/// Process exit codes, stable across releases so scripts can branch on them.
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// The user declined to write, the previous config is unchanged.
+    pub const ABORTED: i32 = 1;
+    pub const INVALID_URL: i32 = 2;
+    pub const NETWORK: i32 = 3;
+    pub const PARSE: i32 = 4;
+    pub const VALIDATION: i32 = 5;
+    pub const INSTALL: i32 = 6;
+    pub const IO: i32 = 7;
+    /// The config was written, but the plan is about to expire or run out of traffic.
+    pub const QUOTA: i32 = 8;
+    /// Invalid command line arguments, `EX_USAGE` from sysexits.h.
+    pub const USAGE: i32 = 64;
+}
+
+impl Error {
+    /// Process exit code for this error category, see [`exit_code`].
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Aborted(_) => exit_code::ABORTED,
+            Error::Url(_) => exit_code::INVALID_URL,
+            Error::Network(_) => exit_code::NETWORK,
+            Error::Parse(_) => exit_code::PARSE,
+            Error::Validation(_) => exit_code::VALIDATION,
+            Error::Install(_) => exit_code::INSTALL,
+            Error::Io(_) => exit_code::IO,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        // reqwest puts the full url, token included, into its messages.
+        let message = redact_error(&e);
+        if e.is_decode() {
+            Error::Parse(message)
+        } else if e.is_redirect() {
+            // The reason set by the redirect policy is only in the source.
+            match std::error::Error::source(&e) {
+                Some(reason) => Error::Network(format!("{message}: {reason}")),
+                None => Error::Network(message),
+            }
+        } else if e.is_timeout() {
+            match e.url() {
+                Some(url) => {
+                    Error::Network(format!("Request timed out: {}", redact_url(url.as_str())))
+                }
+                None => Error::Network(String::from("Request timed out")),
+            }
+        } else {
+            Error::Network(message)
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,47 @@
+//! User scripts run around an update with `--pre-hook` and `--post-hook`.
+
+use tokio::process::Command;
+use tracing::info;
+
+use crate::{Error, Result};
+
+/// Prefix of the environment variables passed to hooks.
+pub const ENV_PREFIX: &str = "EXTERNAL_CONVERTOR_";
+
+/// Run `command` through the shell in the output directory, with `env` added as
+/// `EXTERNAL_CONVERTOR_<NAME>` variables. A non-zero exit is an error.
+pub async fn run_hook(name: &str, command: &str, env: &[(&str, String)]) -> Result<()> {
+    let mut process = match cfg!(windows) {
+        true => {
+            let mut process = Command::new("cmd");
+            process.arg("/C");
+            process
+        }
+        false => {
+            let mut process = Command::new("sh");
+            process.arg("-c");
+            process
+        }
+    };
+    process.arg(command).envs(
+        env.iter()
+            .map(|(key, value)| (format!("{ENV_PREFIX}{key}"), value)),
+    );
+    let output = process
+        .output()
+        .await
+        .map_err(|e| Error::Io(format!("Can't run {name} {command:?}: {e}")))?;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!("  [{name}] {line}");
+    }
+    if !output.status.success() {
+        let mut message = format!("{name} {command:?} failed with {}", output.status);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            message = format!("{message}: {}", stderr.trim());
+        }
+        return Err(Error::Validation(message));
+    }
+    info!("✅ Ran {name}: {command}");
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::{
+    convert::Version,
+    source::{build_client, read_body, send_with_retry, FetchOptions},
+    Error, Result,
+};
+
+const RELEASES_API: &str = "https://api.github.com/repos/SagerNet/sing-box/releases";
+
+/// `$XDG_DATA_HOME/external-convertor/bin`, falling back to `~/.local/share`.
+pub fn managed_bin_dir() -> PathBuf {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from(".local/share"));
+    base.join("external-convertor").join("bin")
+}
+
+fn binary_name() -> &'static str {
+    if cfg!(windows) {
+        "sing-box.exe"
+    } else {
+        "sing-box"
+    }
+}
+
+/// The sing-box installed by [`install_from_github`], if any.
+pub fn managed_sing_box() -> Option<PathBuf> {
+    let path = managed_bin_dir().join(binary_name());
+    path.is_file().then_some(path)
+}
+
+/// Release asset name for this platform, e.g. `sing-box-1.10.7-linux-amd64.tar.gz`.
+fn asset_name(version: &str) -> Result<String> {
+    let os = match env::consts::OS {
+        "macos" => "darwin",
+        os @ ("linux" | "windows" | "freebsd" | "android") => os,
+        os => return Err(Error::Install(format!("No sing-box release for {os}."))),
+    };
+    let arch = match env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "arm" => "armv7",
+        "s390x" => "s390x",
+        arch => return Err(Error::Install(format!("No sing-box release for {arch}."))),
+    };
+    let extension = if os == "windows" { "zip" } else { "tar.gz" };
+    Ok(format!("sing-box-{version}-{os}-{arch}.{extension}"))
+}
+
+pub(super) async fn get(
+    client: &reqwest::Client,
+    url: &str,
+    options: &FetchOptions,
+) -> Result<Vec<u8>> {
+    let response = send_with_retry(client, url, &HeaderMap::new(), options).await?;
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Error fetching {url}: HTTP {}",
+            response.status()
+        )));
+    }
+    let name = url.rsplit('/').next().unwrap_or(url);
+    read_body(response, name).await
+}
+
+/// SHA256 published for `name`, from the asset digest or a checksums asset.
+pub(super) async fn expected_sha256(
+    client: &reqwest::Client,
+    assets: &[Value],
+    name: &str,
+    options: &FetchOptions,
+) -> Result<String> {
+    let asset = assets
+        .iter()
+        .find(|asset| asset["name"].as_str() == Some(name));
+    if let Some(digest) = asset
+        .and_then(|asset| asset["digest"].as_str())
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+    {
+        return Ok(digest.to_lowercase());
+    }
+
+    let checksums = assets.iter().find(|asset| {
+        asset["name"]
+            .as_str()
+            .is_some_and(|name| name.contains("checksums"))
+    });
+    if let Some(url) = checksums.and_then(|asset| asset["browser_download_url"].as_str()) {
+        let content = String::from_utf8_lossy(&get(client, url, options).await?).into_owned();
+        // `<sha256>  <file name>` per line, as written by sha256sum.
+        if let Some(hash) = content.lines().find_map(|line| {
+            let (hash, file) = line.split_once(char::is_whitespace)?;
+            (file.trim().trim_start_matches('*') == name).then(|| hash.to_lowercase())
+        }) {
+            return Ok(hash);
+        }
+    }
+    Err(Error::Install(format!(
+        "No published checksum for {name}, refusing to install it."
+    )))
+}
+
+/// Fail unless `data` hashes to the `expected` SHA256 published for `name`.
+pub(super) fn verify_sha256(data: &[u8], name: &str, expected: &str) -> Result<()> {
+    let actual: String = Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    if actual != expected {
+        return Err(Error::Validation(format!(
+            "Checksum mismatch for {name}: expected {expected}, got {actual}"
+        )));
+    }
+    info!("✅ Verified SHA256: {actual}");
+    Ok(())
+}
+
+/// Download the sing-box release for this platform, latest unless `version` is given.
+///
+/// The archive is verified against its published SHA256 and the binary is placed in
+/// [`managed_bin_dir`].
+pub async fn install_from_github(
+    version: Option<Version>,
+    options: &FetchOptions,
+) -> Result<PathBuf> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("external-convertor"));
+    let client = build_client(options, headers)?;
+
+    let release_url = match version {
+        Some(version) => format!("{RELEASES_API}/tags/v{version}"),
+        None => format!("{RELEASES_API}/latest"),
+    };
+    let release: Value = serde_json::from_slice(&get(&client, &release_url, options).await?)?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| Error::Parse(format!("Release without tag name: {release_url}")))?;
+    let name = asset_name(tag.trim_start_matches('v'))?;
+    let assets = release["assets"].as_array().cloned().unwrap_or_default();
+    let url = assets
+        .iter()
+        .find(|asset| asset["name"].as_str() == Some(&name))
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .ok_or_else(|| Error::Install(format!("Release {tag} has no asset {name}.")))?;
+
+    let expected = expected_sha256(&client, &assets, &name, options).await?;
+    info!("✅ Downloading sing-box {tag}: {url}");
+    let archive = get(&client, url, options).await?;
+    verify_sha256(&archive, &name, &expected)?;
+
+    let bin_dir = managed_bin_dir();
+    let work_dir = bin_dir.join(format!(".{name}"));
+    fs::create_dir_all(&work_dir)?;
+    let result = unpack(&archive, &name, &work_dir, &bin_dir);
+    fs::remove_dir_all(&work_dir)?;
+    result
+}
+
+fn unpack(archive: &[u8], name: &str, work_dir: &Path, bin_dir: &Path) -> Result<PathBuf> {
+    let archive_path = work_dir.join(name);
+    fs::write(&archive_path, archive)?;
+    let exec = Command::new("tar")
+        .arg("-xf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(work_dir)
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run tar: {e}")))?;
+    if !exec.status.success() {
+        return Err(Error::Install(format!(
+            "Failed to unpack {name}: {}",
+            String::from_utf8_lossy(&exec.stderr).trim()
+        )));
+    }
+
+    // Archives hold a single `sing-box-<version>-<os>-<arch>/` directory.
+    let unpacked = work_dir
+        .join(name.trim_end_matches(".tar.gz").trim_end_matches(".zip"))
+        .join(binary_name());
+    if !unpacked.is_file() {
+        return Err(Error::Install(format!("{name} has no {}.", binary_name())));
+    }
+    let target = bin_dir.join(binary_name());
+    fs::rename(&unpacked, &target)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(target)
+}
@@ -0,0 +1,189 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tracing::{info, warn};
+
+use crate::{convert::Version, Error, Result};
+
+mod github;
+mod self_update;
+mod ui;
+
+pub use github::{install_from_github, managed_bin_dir, managed_sing_box};
+pub use self_update::{self_update, SelfUpdate};
+pub use ui::{install_dashboard, DashboardUi};
+
+/// `exec` if given, else sing-box on PATH or the managed one, without installing anything.
+fn sing_box_program(exec: Option<&Path>) -> PathBuf {
+    if let Some(exec) = exec {
+        return exec.to_path_buf();
+    }
+    which("sing-box")
+        .or_else(managed_sing_box)
+        .unwrap_or_else(|| PathBuf::from("sing-box"))
+}
+
+pub fn detect_sing_box_version(exec: Option<&Path>) -> Option<Version> {
+    let exec = Command::new(sing_box_program(exec))
+        .arg("version")
+        .output()
+        .ok()?;
+    if !exec.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&exec.stdout)
+        .lines()
+        .next()?
+        .strip_prefix("sing-box version ")?
+        .parse()
+        .ok()
+}
+
+/// Executable suffixes tried when looking up a bare program name, none outside Windows.
+fn executable_suffixes() -> Vec<String> {
+    if !cfg!(windows) {
+        return vec![String::new()];
+    }
+    let pathext = env::var("PATHEXT").unwrap_or_else(|_| String::from(".COM;.EXE;.BAT;.CMD"));
+    // Names that already carry a suffix, like `sing-box.exe`, are tried as given first.
+    std::iter::once(String::new())
+        .chain(
+            pathext
+                .split(';')
+                .filter(|suffix| !suffix.is_empty())
+                .map(str::to_ascii_lowercase),
+        )
+        .collect()
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Resolve `program` against PATH the way a shell would, without spawning one.
+fn which(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    let suffixes = executable_suffixes();
+    env::split_paths(&path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| {
+            suffixes
+                .iter()
+                .map(move |suffix| dir.join(format!("{program}{suffix}")))
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Package managers able to install sing-box, tried in order on each platform.
+fn package_managers() -> &'static [&'static [&'static str]] {
+    if cfg!(target_os = "macos") {
+        &[&["brew", "install", "sing-box"]]
+    } else if cfg!(target_os = "linux") {
+        &[
+            &["brew", "install", "sing-box"],
+            &["sudo", "apt-get", "install", "-y", "sing-box"],
+            &["sudo", "dnf", "install", "-y", "sing-box"],
+            &["sudo", "pacman", "-S", "--noconfirm", "sing-box"],
+        ]
+    } else if cfg!(target_os = "windows") {
+        &[
+            &["scoop", "install", "sing-box"],
+            &["winget", "install", "sing-box"],
+        ]
+    } else {
+        &[]
+    }
+}
+
+const INSTALL_GUIDE: &str = "run the `install` subcommand or install sing-box manually, see https://sing-box.sagernet.org/installation/package-manager/";
+
+/// Locate the sing-box binary, installing it with the platform package manager when
+/// missing and `install` is set.
+pub fn find_sing_box(install: bool) -> Result<String> {
+    if let Some(path) = which("sing-box").or_else(managed_sing_box) {
+        return Ok(path.display().to_string());
+    }
+
+    if !install {
+        return Err(Error::Install(format!(
+            "sing-box not found, pass --exec or {INSTALL_GUIDE}"
+        )));
+    }
+    warn!("✖ sing-box not found, try install...");
+    let manager = package_managers()
+        .iter()
+        .find(|command| {
+            // `sudo` only elevates, the package manager itself must exist.
+            let program = if command[0] == "sudo" {
+                command[1]
+            } else {
+                command[0]
+            };
+            which(program).is_some()
+        })
+        .ok_or_else(|| {
+            Error::Install(format!(
+                "No supported package manager found, {INSTALL_GUIDE}"
+            ))
+        })?;
+
+    info!("✅ Installing sing-box with: {}", manager.join(" "));
+    // Windows shims like scoop's are `.cmd` files `Command` won't find by bare name.
+    let program = which(manager[0]).unwrap_or_else(|| PathBuf::from(manager[0]));
+    let install_sing_box = Command::new(program)
+        .args(&manager[1..])
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run {}: {e}, {INSTALL_GUIDE}", manager[0])))?;
+
+    if !install_sing_box.status.success() {
+        return Err(Error::Install(format!(
+            "Failed to install sing-box with `{}`: {}\n    {INSTALL_GUIDE}",
+            manager.join(" "),
+            String::from_utf8_lossy(&install_sing_box.stderr).trim()
+        )));
+    }
+    info!("✅ Successfully installed sing-box");
+    which("sing-box")
+        .map(|path| path.display().to_string())
+        .ok_or_else(|| {
+            Error::Install(format!(
+                "sing-box is still not on PATH after installing, {INSTALL_GUIDE}"
+            ))
+        })
+}
+
+pub fn check_config(path: &str, exec: Option<&Path>) -> Result<()> {
+    let exec = Command::new(sing_box_program(exec))
+        .arg("check")
+        .arg("-c")
+        .arg(path)
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run sing-box check: {e}")))?;
+
+    if exec.status.success() {
+        return Ok(());
+    }
+
+    let mut message = format!("sing-box check failed for {path}:");
+    for line in String::from_utf8_lossy(&exec.stderr)
+        .lines()
+        .chain(String::from_utf8_lossy(&exec.stdout).lines())
+        .filter(|line| !line.trim().is_empty())
+    {
+        message.push_str("\n    ");
+        message.push_str(line.trim());
+    }
+    Err(Error::Validation(message))
+}
@@ -0,0 +1,120 @@
+use std::{env, fs, path::Path, process::Command};
+
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use serde_json::Value;
+use tracing::info;
+
+use super::github::{expected_sha256, get, verify_sha256};
+use crate::{convert::Version, source::build_client, source::FetchOptions, Error, Result};
+
+const RELEASES_API: &str =
+    "https://api.github.com/repos/MasakiMu319/External-Convertor/releases/latest";
+
+fn binary_name() -> &'static str {
+    if cfg!(windows) {
+        "external_convertor.exe"
+    } else {
+        "external_convertor"
+    }
+}
+
+/// Release asset for this platform, e.g. `external_convertor-linux-x86_64.tar.gz`.
+///
+/// Archives hold the binary at their root.
+fn asset_name() -> String {
+    let extension = if cfg!(windows) { "zip" } else { "tar.gz" };
+    format!(
+        "external_convertor-{}-{}.{extension}",
+        env::consts::OS,
+        env::consts::ARCH
+    )
+}
+
+/// Outcome of [`self_update`].
+#[derive(Debug)]
+pub enum SelfUpdate {
+    UpToDate(Version),
+    /// A newer release exists, but `check` was set.
+    Available(Version),
+    Updated(Version),
+}
+
+/// Replace the running executable with the latest release if it is newer.
+///
+/// The archive is verified against its published SHA256 before anything is touched,
+/// with `check` only the version is compared.
+pub async fn self_update(check: bool, options: &FetchOptions) -> Result<SelfUpdate> {
+    let current: Version = env!("CARGO_PKG_VERSION").parse().map_err(Error::Parse)?;
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("external-convertor"));
+    let client = build_client(options, headers)?;
+
+    let release: Value = serde_json::from_slice(&get(&client, RELEASES_API, options).await?)?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| Error::Parse(String::from("Latest release without tag name")))?;
+    let latest: Version = tag.parse().map_err(Error::Parse)?;
+    if latest <= current {
+        return Ok(SelfUpdate::UpToDate(current));
+    }
+    if check {
+        return Ok(SelfUpdate::Available(latest));
+    }
+
+    let name = asset_name();
+    let assets = release["assets"].as_array().cloned().unwrap_or_default();
+    let url = assets
+        .iter()
+        .find(|asset| asset["name"].as_str() == Some(&name))
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .ok_or_else(|| Error::Install(format!("Release {tag} has no asset {name}.")))?;
+    let expected = expected_sha256(&client, &assets, &name, options).await?;
+    info!("✅ Downloading external_convertor {tag}: {url}");
+    let archive = get(&client, url, options).await?;
+    verify_sha256(&archive, &name, &expected)?;
+
+    let exe = env::current_exe()?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| Error::Install(format!("{} has no parent directory.", exe.display())))?;
+    let work_dir = dir.join(format!(".{name}"));
+    fs::create_dir_all(&work_dir)?;
+    let result = replace_executable(&archive, &name, &work_dir, &exe);
+    fs::remove_dir_all(&work_dir)?;
+    result.map(|()| SelfUpdate::Updated(latest))
+}
+
+fn replace_executable(archive: &[u8], name: &str, work_dir: &Path, exe: &Path) -> Result<()> {
+    let archive_path = work_dir.join(name);
+    fs::write(&archive_path, archive)?;
+    let exec = Command::new("tar")
+        .arg("-xf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(work_dir)
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run tar: {e}")))?;
+    if !exec.status.success() {
+        return Err(Error::Install(format!(
+            "Failed to unpack {name}: {}",
+            String::from_utf8_lossy(&exec.stderr).trim()
+        )));
+    }
+    let unpacked = work_dir.join(binary_name());
+    if !unpacked.is_file() {
+        return Err(Error::Install(format!("{name} has no {}.", binary_name())));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&unpacked, fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows can't overwrite a running executable, but it can rename it away.
+    if cfg!(windows) {
+        let old = exe.with_extension("old.exe");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+    }
+    fs::rename(&unpacked, exe)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::{fs, path::Path, process::Command};
+
+use clap::ValueEnum;
+use reqwest::header::HeaderMap;
+use tracing::info;
+
+use crate::{
+    source::{build_client, read_body, send_with_retry, FetchOptions},
+    Error, Result,
+};
+
+/// Web dashboards served by the clash API from `external_ui`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DashboardUi {
+    Yacd,
+    Metacubexd,
+}
+
+impl DashboardUi {
+    /// Tarball of the built site, holding a single top-level directory.
+    fn archive_url(&self) -> &'static str {
+        match self {
+            DashboardUi::Yacd => {
+                "https://github.com/MetaCubeX/Yacd-meta/archive/refs/heads/gh-pages.tar.gz"
+            }
+            DashboardUi::Metacubexd => {
+                "https://github.com/MetaCubeX/metacubexd/archive/refs/heads/gh-pages.tar.gz"
+            }
+        }
+    }
+}
+
+/// Download `ui` into `dir` unless it already holds a dashboard, returning whether it did.
+pub async fn install_dashboard(
+    ui: DashboardUi,
+    dir: &Path,
+    options: &FetchOptions,
+) -> Result<bool> {
+    if dir.join("index.html").is_file() {
+        return Ok(false);
+    }
+    let client = build_client(options, HeaderMap::new())?;
+    let response = send_with_retry(&client, ui.archive_url(), &HeaderMap::new(), options).await?;
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Error fetching dashboard {ui:?}: HTTP {}",
+            response.status()
+        )));
+    }
+    let archive = read_body(response, "dashboard").await?;
+
+    fs::create_dir_all(dir)?;
+    let archive_path = dir.join(".dashboard.tar.gz");
+    fs::write(&archive_path, archive)?;
+    let exec = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(dir)
+        .arg("--strip-components=1")
+        .output();
+    fs::remove_file(&archive_path)?;
+    let exec = exec.map_err(|e| Error::Install(format!("Can't run tar: {e}")))?;
+    if !exec.status.success() {
+        return Err(Error::Install(format!(
+            "Failed to unpack dashboard: {}",
+            String::from_utf8_lossy(&exec.stderr).trim()
+        )));
+    }
+    info!("✅ Installed {ui:?} dashboard to: {}", dir.display());
+    Ok(true)
+}
@@ -0,0 +1,54 @@
+//! Subscription urls kept in the macOS Keychain instead of `profiles.toml`.
+
+use crate::{Error, Result};
+
+/// Keychain service the urls are stored under, the profile name is the account.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const SERVICE: &str = "external-convertor";
+
+/// Store the urls of profile `name`, one per line, replacing any stored before.
+#[cfg(target_os = "macos")]
+pub fn store_urls(name: &str, urls: &[String]) -> Result<()> {
+    security_framework::passwords::set_generic_password(SERVICE, name, urls.join("\n").as_bytes())
+        .map_err(|e| Error::Io(format!("Can't store profile {name} in the Keychain: {e}")))
+}
+
+#[cfg(target_os = "macos")]
+pub fn load_urls(name: &str) -> Result<Vec<String>> {
+    let secret = security_framework::passwords::get_generic_password(SERVICE, name)
+        .map_err(|e| Error::Io(format!("Can't read profile {name} from the Keychain: {e}")))?;
+    Ok(String::from_utf8_lossy(&secret)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+pub fn delete_urls(name: &str) -> Result<()> {
+    security_framework::passwords::delete_generic_password(SERVICE, name).map_err(|e| {
+        Error::Io(format!(
+            "Can't delete profile {name} from the Keychain: {e}"
+        ))
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn unsupported() -> Error {
+    Error::Validation(String::from("The Keychain is only available on macOS."))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn store_urls(_name: &str, _urls: &[String]) -> Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn load_urls(_name: &str) -> Result<Vec<String>> {
+    Err(unsupported())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn delete_urls(_name: &str) -> Result<()> {
+    Err(unsupported())
+}
@@ -0,0 +1,32 @@
+//! Convert proxy subscriptions into configs usable as a Surge external proxy.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+pub mod convert;
+pub mod dashboard;
+pub mod doctor;
+pub mod encryption;
+pub mod error;
+pub mod hook;
+pub mod install;
+pub mod keychain;
+pub mod metrics;
+pub mod notify;
+pub mod output;
+pub mod picker;
+pub mod profile;
+pub mod serve;
+pub mod service;
+pub mod settings;
+pub mod source;
+pub mod speedtest;
+
+pub use convert::{Converter, Node};
+pub use error::{Error, Result};
+pub use output::Target;
+pub use source::Subscription;
+
+/// A sing-box config as its top-level sections.
+pub type Config = HashMap<String, Value>;
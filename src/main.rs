@@ -1,202 +1,1798 @@
-use std::{collections::HashMap, env, fs::File, io::Write, process::Command};
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs,
+    io::{IsTerminal, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use clap::Parser;
-use regex::Regex;
-use reqwest::{
-    blocking::Client,
-    header::{HeaderMap, HeaderValue, USER_AGENT},
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{
+    builder::PossibleValuesParser, parser::ValueSource, ArgAction, ArgMatches, CommandFactory,
+    FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use clap_complete::Shell;
+use external_convertor::{
+    convert::{
+        find_clash_api, inbound_port, load_overrides, load_patch, load_template, move_inbound_port,
+        port_conflict, remove_outbounds, rename_outbounds, validate_config, ClashApiOptions,
+        DnsPreset, Dropped, LatencyOptions, Node, RenameMap, RoutePreset, SortKey, Summary,
+        Version, DEFAULT_INBOUND, STRIPPABLE,
+    },
+    dashboard::dashboard,
+    doctor::{doctor, DoctorOptions},
+    encryption::{default_key_path, Encryption},
+    error::exit_code,
+    hook::run_hook,
+    install::{
+        check_config, detect_sing_box_version, find_sing_box, install_dashboard,
+        install_from_github, self_update, DashboardUi, SelfUpdate,
+    },
+    keychain,
+    metrics::{serve_metrics, Metrics},
+    notify::{notify, Event, NotifyOptions},
+    output::{
+        config_path, config_unchanged, copy_to_clipboard, diff_against_existing, existing_config,
+        export_wireguard, format_timestamp, git_commit_config, lock_output, qr_code,
+        record_history, restore_history, save_client_configs, save_config, share_link,
+        surge::external_line, CONFIG_FILE, OVERRIDE_FILE,
+    },
+    picker::pick_nodes,
+    profile::{default_profiles_path, Profile, Profiles},
+    serve::{serve, ServeState},
+    service::{install_service, reload_sing_box, uninstall_service, Reload, ServiceOptions},
+    settings::{default_settings_path, load_settings},
+    source::{
+        cache::{default_cache_dir, format_age, now},
+        check_url, format_bytes, redact_url, FetchOptions, Quota,
+    },
+    speedtest::{speed_test, SpeedTestOptions},
+    Config, Converter, Error, Result, Subscription, Target,
 };
-use serde_json::Value;
-use url::Url;
+use serde::Serialize;
+use tracing::{error, info, level_filters::LevelFilter, warn};
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, default_value = Some("sing-box"), value_name = "TYPE")]
-    client: Option<String>,
-    #[arg(short, long, value_name = "SUBSCRIPTION")]
-    url: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Print the result as text logs or as one JSON object per update on stdout.
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    format: OutputFormat,
+    /// Log more details, -v for debug and -vv for trace output.
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Don't color log output, also disabled by NO_COLOR or when not writing to a terminal.
+    #[arg(long, global = true)]
+    no_color: bool,
+    /// Log without colors and without the ✅/✖ prefixes.
+    #[arg(long, global = true)]
+    plain: bool,
+    /// Only log warnings and errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Clients to write configs for, comma separated.
+    ///
+    /// The sing-box config.json is always written, Surge runs it and the other
+    /// configs are derived from it without fetching again.
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value = "sing-box",
+        value_name = "TYPE",
+        value_delimiter = ',',
+        env = "EC_CLIENT"
+    )]
+    client: Vec<Target>,
+    /// Subscription url, repeat to merge several subscriptions.
+    ///
+    /// EC_SUB_URL keeps the url out of the process list.
+    #[arg(
+        short,
+        long,
+        value_name = "SUBSCRIPTION",
+        env = "EC_SUB_URL",
+        hide_env_values = true
+    )]
+    url: Vec<String>,
+    /// Settings file with default options, defaults to ~/.config/external-convertor/config.toml.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Directory the config, history and rule-sets are written to, defaults to the current one.
+    #[arg(short, long, value_name = "DIR", env = "EC_OUTPUT")]
+    out_dir: Option<PathBuf>,
+    /// Accept plain-HTTP, IP or localhost subscription urls for this host (`*` for any), repeatable.
+    #[arg(long = "allow-host", value_name = "HOST", global = true)]
+    allowed_hosts: Vec<String>,
+    /// Print the full subscription url instead of masking its tokens.
+    #[arg(long, global = true)]
+    show_url: bool,
+    /// User-Agent for fetching the subscription, defaults to one matching the client.
+    #[arg(long, value_name = "UA")]
+    user_agent: Option<String>,
+    /// Extra request header for the subscription, e.g. 'Name: value', repeatable.
+    #[arg(short = 'H', long = "header", value_name = "HEADER", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+    /// Send `Authorization: Bearer <TOKEN>` with the subscription request.
+    #[arg(long, value_name = "TOKEN")]
+    bearer: Option<String>,
+    /// Extra fetch attempts on 5xx, 429, timeouts and connection errors.
+    #[arg(long, default_value_t = 2, value_name = "N")]
+    retries: u32,
+    /// Base delay between retries in seconds, doubled on every attempt.
+    #[arg(long, default_value = "1", value_name = "SECS", value_parser = parse_retry_delay)]
+    retry_delay: Duration,
+    /// Fetch through this proxy, e.g. socks5://127.0.0.1:1080, HTTPS_PROXY is honored otherwise.
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+    /// Request timeout in seconds, 0 disables it.
+    #[arg(long, default_value_t = 30, value_name = "SECS")]
+    timeout: u64,
+    /// Redirects followed per request before failing.
+    #[arg(long, default_value_t = 10, value_name = "N")]
+    max_redirects: usize,
+    /// Refuse plain-HTTP urls and redirects, even for --allow-host hosts.
+    #[arg(long)]
+    https_only: bool,
+    /// Accept invalid or self-signed TLS certificates, e.g. of a self-hosted panel.
+    #[arg(long)]
+    insecure: bool,
+    /// Directory caching subscription responses between runs.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Don't cache subscription responses or send conditional requests.
+    #[arg(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+    /// Use the cached subscription when fetching fails.
+    #[arg(long, conflicts_with = "no_cache")]
+    offline_fallback: bool,
+    /// Encrypt cached subscriptions and history backups with an age key, they hold node credentials.
+    #[arg(long)]
+    encrypt: bool,
+    /// age identity used by --encrypt, generated if missing, defaults to ~/.config/external-convertor/key.txt.
+    #[arg(long, value_name = "FILE", requires = "encrypt")]
+    encrypt_key: Option<PathBuf>,
+    /// Key loaded for --encrypt.
+    #[arg(skip)]
+    encryption: Option<Encryption>,
+    /// Regenerate the config even if the provider reports no changes.
+    #[arg(short, long)]
+    force: bool,
+    /// Inject an experimental.clash_api block into the output config.
+    #[arg(long)]
+    clash_api: bool,
+    #[arg(long, default_value = "127.0.0.1:9090", value_name = "ADDRESS")]
+    clash_api_address: String,
+    /// Secret for the clash API, the previous config's or a random one if omitted.
+    #[arg(long, value_name = "SECRET")]
+    clash_api_secret: Option<String>,
+    #[arg(long, value_name = "DIR")]
+    external_ui: Option<String>,
+    /// Download this web dashboard into --external-ui (default ui) and serve it.
+    #[arg(long, value_enum, value_name = "UI", requires = "clash_api")]
+    dashboard_ui: Option<DashboardUi>,
+    /// Listen address of the mixed inbound, added if the subscription has none.
+    #[arg(long, value_name = "ADDRESS")]
+    listen: Option<String>,
+    /// Listen on the IPv6 loopback, shorthand for --listen ::1.
+    #[arg(long, conflicts_with = "listen")]
+    listen_ipv6: bool,
+    /// Mixed inbound backing the external proxy when there are several, by tag.
+    #[arg(long, value_name = "TAG")]
+    inbound_tag: Option<String>,
+    /// Mixed inbound added when the subscription has none.
+    #[arg(long, default_value_t = DEFAULT_INBOUND, value_name = "ADDRESS:PORT")]
+    default_inbound: SocketAddr,
+    /// Listen port of the mixed inbound, added if the subscription has none.
+    #[arg(long, value_name = "PORT")]
+    port: Option<u16>,
+    /// Move the mixed inbound to a free port when its port is taken.
+    #[arg(long)]
+    auto_port: bool,
+    /// Drop these top-level sections of the subscription, e.g. log,experimental,ntp.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "SECTION",
+        value_parser = PossibleValuesParser::new(STRIPPABLE)
+    )]
+    strip: Vec<String>,
+    /// Base config deep-merged with the fetched subscription.
+    #[arg(short, long, value_name = "FILE")]
+    template: Option<PathBuf>,
+    /// RFC 6902 JSON patch applied to the final config before writing.
+    #[arg(short, long, value_name = "FILE")]
+    patch: Option<PathBuf>,
+    /// Ignore override.json in the output directory.
+    #[arg(long)]
+    no_override: bool,
+    /// TOML file with `[exact]` and `[[regex]]` node renames, references follow along.
+    #[arg(long, value_name = "FILE")]
+    rename_map: Option<PathBuf>,
+    /// Download remote rule-sets and reference the local copies instead.
+    #[arg(long)]
+    download_rule_sets: bool,
+    #[arg(long, default_value = "rule-set", value_name = "DIR")]
+    rule_set_dir: PathBuf,
+    /// Replace the dns section with a curated preset.
+    #[arg(long, value_enum, value_name = "PRESET")]
+    dns: Option<DnsPreset>,
+    /// Replace the route section with a curated preset.
+    #[arg(long, value_enum, value_name = "PRESET")]
+    ruleset: Option<RoutePreset>,
+    /// Reject ad domains, with --ruleset.
+    #[arg(long, requires = "ruleset")]
+    block_ads: bool,
+    /// Probe every node with a TCP connect and append the latency to its tag.
+    #[arg(long)]
+    test_latency: bool,
+    /// Drop nodes slower than this many milliseconds instead of annotating them.
+    #[arg(long, value_name = "MS", requires = "test_latency")]
+    max_latency: Option<u64>,
+    /// Warn, notify and exit with code 8 when the plan expires within this many days.
+    #[arg(long, value_name = "DAYS")]
+    warn_expiry_days: Option<u64>,
+    /// Warn, notify and exit with code 8 once this much of the traffic is used.
+    #[arg(long, value_name = "PERCENT")]
+    warn_traffic_percent: Option<f64>,
+    /// Keep only nodes of these outbound types, e.g. vless,hysteria2.
+    #[arg(long, value_delimiter = ',', value_name = "TYPES")]
+    protocols: Vec<String>,
+    /// Order nodes and group members, latency needs --test-latency.
+    #[arg(long, value_enum, value_name = "KEY")]
+    sort: Option<SortKey>,
+    /// Prefix node tags with the flag of the country their server resolves to.
+    #[arg(long)]
+    geoip: bool,
+    /// MaxMind-format country database, downloaded there if missing.
+    #[arg(long, value_name = "FILE", requires = "geoip")]
+    geoip_db: Option<PathBuf>,
+    /// Generate selector and urltest groups when the subscription has none.
+    #[arg(long)]
+    groups: bool,
+    /// Also generate a urltest group per detected region.
+    #[arg(long, requires = "groups")]
+    region_groups: bool,
+    /// sing-box version to migrate the config for, detected from the local binary if omitted.
+    #[arg(long, value_name = "VERSION")]
+    target_version: Option<Version>,
+    /// sing-box binary used for the Surge line, version detection and checks.
+    #[arg(long, value_name = "PATH", global = true)]
+    exec: Option<PathBuf>,
+    /// Never install sing-box when it can't be found.
+    #[arg(long, global = true, conflicts_with = "exec")]
+    no_install: bool,
+    /// Pin sing-box to this version for `install` and config migration.
+    #[arg(long, value_name = "VERSION", global = true)]
+    sing_box_version: Option<Version>,
+    /// Validate the new config with `sing-box check` before it replaces the old one.
+    #[arg(long)]
+    check: bool,
+    /// Pick, rename and group nodes interactively before writing.
+    #[arg(long, conflicts_with = "daemon")]
+    pick: bool,
+    /// Copy the Surge [Proxy] line to the clipboard.
+    #[arg(long)]
+    copy: bool,
+    /// Print a QR code of the share link of every node.
+    #[arg(long)]
+    qr: bool,
+    /// Also write a wg-quick .conf per WireGuard outbound into this directory.
+    #[arg(long, value_name = "DIR")]
+    wireguard_dir: Option<PathBuf>,
+    /// Keep the last N generated configs under the history directory.
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    history: usize,
+    /// Print the changes against the existing config before writing.
+    #[arg(long)]
+    diff: bool,
+    /// Show the diff and ask for confirmation before writing.
+    #[arg(long)]
+    confirm: bool,
+    /// Commit the written config when the output directory is a git repo.
+    #[arg(long)]
+    git: bool,
+    /// POST a JSON notification here on updates, failures and node changes (daemon and serve).
+    #[arg(long, value_name = "URL")]
+    notify_webhook: Option<String>,
+    /// Telegram bot token to send notifications with, needs --telegram-chat.
+    #[arg(long, value_name = "TOKEN", requires = "telegram_chat")]
+    telegram_token: Option<String>,
+    #[arg(long, value_name = "CHAT_ID", requires = "telegram_token")]
+    telegram_chat: Option<String>,
+    /// Show desktop notifications.
+    #[arg(long)]
+    notify_desktop: bool,
+    /// Reload a running sing-box after writing a new config.
+    #[arg(long)]
+    reload: bool,
+    /// Shell command run before fetching, a non-zero exit skips the update.
+    #[arg(long, value_name = "COMMAND")]
+    pre_hook: Option<String>,
+    /// Shell command run after a new config is written, with its path in
+    /// EXTERNAL_CONVERTOR_OUTPUT and the summary as JSON in EXTERNAL_CONVERTOR_SUMMARY.
+    #[arg(long, value_name = "COMMAND")]
+    post_hook: Option<String>,
+    /// Keep running and update the config every --interval.
+    #[arg(long, conflicts_with_all = ["confirm", "restore"])]
+    daemon: bool,
+    /// Time between daemon updates, e.g. 90s, 30m, 6h or 1d.
+    #[arg(long, default_value = "6h", value_name = "DURATION", value_parser = parse_interval, requires = "daemon")]
+    interval: Duration,
+    /// Serve Prometheus metrics on http://ADDRESS/metrics while running as a daemon.
+    #[arg(long, value_name = "ADDRESS", requires = "daemon")]
+    metrics_listen: Option<SocketAddr>,
+    /// Restore the N-th newest config from history (1 is the latest) and exit.
+    #[arg(long, value_name = "N", conflicts_with = "url")]
+    restore: Option<usize>,
 }
 
-fn check_url(sub_url: &str) -> Result<String, String> {
-    let sub_url = sub_url.to_lowercase();
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Fetch, convert and write the configs, what running without a subcommand does.
+    Convert,
+    /// Fetch and parse the subscriptions, printing their nodes as a sing-box config.
+    Fetch {
+        /// Write the nodes to this file instead of stdout.
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Validate an existing config without refetching, with `sing-box check` when available.
+    Validate {
+        /// Config to validate, the one in the output directory by default.
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Manage named subscriptions.
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Convert the subscription of a profile, the default one if no name is given.
+    Update {
+        name: Option<String>,
+        /// Convert every profile concurrently, each in its own process.
+        #[arg(long, conflicts_with = "name")]
+        all: bool,
+    },
+    /// Download sing-box from GitHub releases into the managed bin directory.
+    Install,
+    /// Replace this executable with the latest GitHub release, verified by its SHA256.
+    SelfUpdate {
+        /// Only report whether a newer release exists.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print a shell completion script.
+    Completions { shell: Shell },
+    /// Check sing-box, output directory, subscription hosts and ports, suggesting fixes.
+    Doctor,
+    /// Serve conversions over HTTP at `/sub?url=...&target=sing-box|clash|surge|surfboard|xray`.
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:25500", value_name = "ADDRESS")]
+        listen: SocketAddr,
+    },
+    /// Run `update` on a schedule as a background service.
+    #[command(subcommand)]
+    Service(ServiceCommand),
+    /// Monitor traffic and connections through the clash API of a generated config.
+    Dashboard {
+        /// Config whose clash API to connect to, the one in the output directory by default.
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Print the nodes of a sing-box config as share links.
+    Export {
+        /// Config to export, the one in the output directory by default.
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+        /// Print a base64 subscription instead of one link per line.
+        #[arg(long)]
+        base64: bool,
+    },
+    /// Measure download throughput of every node in the generated config.
+    Speedtest {
+        /// Config to test, the one in the output directory by default.
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+        /// File downloaded through each node.
+        #[arg(
+            long,
+            default_value = "https://speed.cloudflare.com/__down?bytes=100000000",
+            value_name = "URL"
+        )]
+        test_url: String,
+        /// Time spent downloading through each node, e.g. 10s.
+        #[arg(long, default_value = "10s", value_name = "DURATION", value_parser = parse_interval)]
+        duration: Duration,
+    },
+}
 
-    match Url::parse(&sub_url) {
-        Ok(parsed_url) => {
-            if !["http", "https"].contains(&parsed_url.scheme()) {
-                return Err(String::from("Only support http or https."));
-            }
+#[derive(Debug, Subcommand)]
+enum ServiceCommand {
+    /// Install and load the service, replacing an installed one.
+    Install {
+        /// Profile to update, the default one if omitted.
+        profile: Option<String>,
+        /// Time between updates, e.g. 30m, 6h or 1d.
+        #[arg(long, default_value = "6h", value_name = "DURATION", value_parser = parse_interval)]
+        interval: Duration,
+        /// Install system-wide systemd units instead of user ones.
+        #[arg(long)]
+        system: bool,
+    },
+    /// Stop and remove the service.
+    Uninstall {
+        /// Remove the system-wide systemd units.
+        #[arg(long)]
+        system: bool,
+    },
+}
 
-            if parsed_url.host_str().is_none() {
-                return Err(String::from("Invalid url without host name."));
-            }
+#[derive(Debug, Subcommand)]
+enum ProfileCommand {
+    /// Save a subscription under a name, replacing any profile with the same name.
+    Add {
+        name: String,
+        /// Subscription url, repeat to merge several subscriptions.
+        #[arg(short, long, value_name = "SUBSCRIPTION", required = true)]
+        url: Vec<String>,
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "sing-box",
+            value_name = "TYPE"
+        )]
+        client: Target,
+        /// Directory the config is written to.
+        #[arg(short, long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+        /// Keep the urls in the macOS Keychain instead of the profiles file.
+        #[arg(long)]
+        keychain: bool,
+    },
+    /// List saved profiles.
+    List,
+    /// Delete a profile.
+    Remove { name: String },
+    /// Make a profile the default for `update`.
+    Use { name: String },
+}
 
-            let url_regex = Regex::new(r"^https?://[-a-zA-Z0-9@:%._\+~#=]{2,256}\.[a-z]{2,6}\b([-a-zA-Z0-9@:%_\+.~#?&//=]*)$").unwrap();
-            if !url_regex.is_match(&sub_url) {
-                return Err(format!("Invalid url, please check again."));
+fn run_profile(command: ProfileCommand, cli: &Args) -> Result<()> {
+    let path = default_profiles_path();
+    let mut profiles = Profiles::load(&path)?;
+    match command {
+        ProfileCommand::Add {
+            name,
+            url,
+            client,
+            out_dir,
+            keychain,
+        } => {
+            let mut url = url
+                .iter()
+                .map(|url| check_url(url, &cli.allowed_hosts))
+                .collect::<Result<Vec<_>>>()?;
+            let out_dir = out_dir.map(std::path::absolute).transpose()?;
+            if keychain {
+                keychain::store_urls(&name, &url)?;
+                info!("✅ Stored the urls of {name} in the Keychain.");
+                url.clear();
+            } else if profiles.profiles.get(&name).is_some_and(|old| old.keychain) {
+                keychain::delete_urls(&name)?;
             }
+            profiles.profiles.insert(
+                name.clone(),
+                Profile {
+                    url,
+                    client,
+                    out_dir,
+                    keychain,
+                },
+            );
+            if profiles.default.is_none() {
+                profiles.default = Some(name.clone());
+            }
+            profiles.save(&path)?;
+            info!("✅ Saved profile {name} to: {}", path.display());
+        }
+        ProfileCommand::List => {
+            if profiles.profiles.is_empty() {
+                info!("✅ No profiles, add one with `profile add`.");
+            }
+            for (name, profile) in &profiles.profiles {
+                let marker = if profiles.default.as_deref() == Some(name) {
+                    "*"
+                } else {
+                    " "
+                };
+                let mut urls: Vec<String> = profile
+                    .url
+                    .iter()
+                    .map(|url| {
+                        if cli.show_url {
+                            url.clone()
+                        } else {
+                            redact_url(url)
+                        }
+                    })
+                    .collect();
+                if profile.keychain {
+                    urls.push(String::from("<keychain>"));
+                }
+                let mut line = format!(
+                    "{marker} {name}: {} ({})",
+                    urls.join(", "),
+                    profile.client.to_possible_value().unwrap().get_name()
+                );
+                if let Some(dir) = &profile.out_dir {
+                    line.push_str(&format!(" -> {}", dir.display()));
+                }
+                println!("{line}");
+            }
+        }
+        ProfileCommand::Remove { name } => {
+            profiles.remove(&name)?;
+            profiles.save(&path)?;
+            info!("✅ Removed profile {name}.");
+        }
+        ProfileCommand::Use { name } => {
+            profiles.get(Some(&name))?;
+            profiles.default = Some(name.clone());
+            profiles.save(&path)?;
+            info!("✅ Default profile is now: {name}");
+        }
+    }
+    Ok(())
+}
 
-            Ok(sub_url)
+fn run_service(command: ServiceCommand, cli: &Args) -> Result<()> {
+    match command {
+        ServiceCommand::Install {
+            profile,
+            interval,
+            system,
+        } => {
+            let profile_dir = Profiles::load(&default_profiles_path())?
+                .get(profile.as_deref())
+                .ok()
+                .and_then(|(_, profile)| profile.out_dir.clone());
+            let out_dir = match profile_dir.or_else(|| cli.out_dir.clone()) {
+                Some(dir) => fs::canonicalize(&dir).or_else(|_| std::path::absolute(dir))?,
+                None => env::current_dir()?,
+            };
+            let options = ServiceOptions {
+                program: env::current_exe()?,
+                args: [String::from("--reload"), String::from("update")]
+                    .into_iter()
+                    .chain(profile)
+                    .collect(),
+                working_dir: env::current_dir()?,
+                interval,
+                system,
+                config_path: out_dir.join(CONFIG_FILE),
+                sing_box: sing_box_exec(cli).ok().map(PathBuf::from),
+            };
+            for path in install_service(&options)? {
+                info!("✅ Installed service file: {}", path.display());
+            }
+            if options.sing_box.is_none() && cfg!(target_os = "linux") {
+                warn!("✖ sing-box not found, skip its service unit.");
+            }
+            info!(
+                "✅ Service updates the config every {}.",
+                format_age(interval.as_secs())
+            );
+        }
+        ServiceCommand::Uninstall { system } => {
+            for path in uninstall_service(system)? {
+                info!("✅ Removed service file: {}", path.display());
+            }
         }
-        Err(e) => Err(format!("URL parse failed: {e}")),
     }
+    Ok(())
 }
 
-fn fetch_subscription(sub_url: &str) -> Result<HashMap<String, Value>, Box<dyn std::error::Error>> {
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static("sing-box/1.6.0"));
-    let client = Client::builder().default_headers(headers).build()?;
-    let response = client.get(sub_url).send()?;
+/// Override the subscription options not given on the command line with a profile.
+fn apply_profile(cli: &mut Args, matches: &ArgMatches, name: Option<&str>) -> Result<()> {
+    let profiles = Profiles::load(&default_profiles_path())?;
+    let (name, profile) = profiles.get(name)?;
+    info!("✅ Using profile: {name}");
 
-    if response.status().is_success() {
-        let json_resp: Value = response.json()?;
-        let data: std::collections::HashMap<String, Value> = serde_json::from_value(json_resp)?;
-        Ok(data)
-    } else {
-        Err(format!("Error fetching subscription: HTTP {}", response.status()).into())
+    let from_cli = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if !from_cli("url") {
+        cli.url = profile.urls(name)?;
+    }
+    if !from_cli("client") {
+        cli.client = vec![profile.client];
     }
+    if !from_cli("out_dir") && profile.out_dir.is_some() {
+        cli.out_dir = profile.out_dir.clone();
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Outcome of one update, printed with `--format json`.
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    /// Absolute path of the config, `None` when nothing was written.
+    output: Option<PathBuf>,
+    nodes: usize,
+    controller: Option<Controller>,
+    surge: Option<String>,
+    clash_api: Option<String>,
+    /// Dashboard url with the controller and secret filled in.
+    dashboard: Option<String>,
+    quota: Vec<Quota>,
+    warnings: Vec<String>,
+    /// Configs of the other `--client` targets.
+    outputs: Vec<PathBuf>,
+    /// wg-quick configs exported with `--wireguard-dir`.
+    wireguard: Vec<PathBuf>,
+    /// Expiry and traffic warnings from --warn-expiry-days and --warn-traffic-percent.
+    quota_alerts: Vec<String>,
+    /// Nodes by protocol and region, dropped nodes and the chosen inbound.
+    summary: Option<Summary>,
+    /// Node tags, compared between daemon runs.
+    #[serde(skip)]
+    tags: Vec<String>,
 }
 
-#[derive(Debug, Default)]
-struct ExternalController {
+#[derive(Debug, Serialize)]
+struct Controller {
     address: String,
     port: String,
 }
 
-fn save_config(
-    mut data: HashMap<String, Value>,
-) -> Result<ExternalController, Box<dyn std::error::Error>> {
-    let inbounds = data.get("inbounds");
+impl Report {
+    fn warn(&mut self, message: String) {
+        warn!("✖ {message}");
+        self.warnings.push(message);
+    }
+}
 
-    if inbounds.is_none() {
-        return Err(format!("Can't find any inbounds in target configuration.").into());
+fn print_json(cli: &Args, value: &impl Serialize) {
+    if cli.format == OutputFormat::Json {
+        match serde_json::to_string(value) {
+            Ok(line) => println!("{line}"),
+            Err(e) => error!("✖ Can't serialize result: {e}"),
+        }
+    }
+}
+
+fn parse_interval(interval: &str) -> std::result::Result<Duration, String> {
+    let interval = interval.trim();
+    let split = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (value, unit) = interval.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| String::from("expected e.g. 90s, 30m, 6h or 1d"))?;
+    let secs = match unit {
+        "" | "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        "d" => value * 86400,
+        _ => return Err(format!("unknown unit {unit}, expected s, m, h or d")),
+    };
+    if secs == 0 {
+        return Err(String::from("interval must be positive"));
     }
+    Ok(Duration::from_secs(secs))
+}
+
+fn parse_retry_delay(secs: &str) -> std::result::Result<Duration, String> {
+    let secs: f64 = secs
+        .trim()
+        .parse()
+        .map_err(|_| String::from("expected seconds, e.g. 1 or 0.5"))?;
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| String::from("expected a finite, non-negative number of seconds"))
+}
+
+fn parse_header(header: &str) -> std::result::Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| String::from("expected 'Name: value'"))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Fill options not given on the command line from the settings file.
+fn apply_settings(cli: &mut Args, matches: &ArgMatches) -> Result<()> {
+    let path = match &cli.config {
+        Some(path) => path.clone(),
+        None => {
+            let path = default_settings_path();
+            if !path.exists() {
+                return Ok(());
+            }
+            path
+        }
+    };
+    let settings = load_settings(&path)?;
+    info!("✅ Loaded settings from: {}", path.display());
 
-    let mut controller_info = ExternalController::default();
-    let mut new_inbound = Vec::new();
-    for inbound in inbounds.unwrap().as_array().unwrap() {
-        let inbound_map: std::collections::HashMap<String, Value> =
-            serde_json::from_value(inbound.clone()).unwrap();
-        if inbound_map.get("type").is_some()
-            && inbound_map
-                .get("type")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .eq("mixed")
-        {
-            new_inbound.push(inbound.clone());
-            controller_info.address = inbound_map
-                .get("listen")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string();
-            controller_info.port = inbound_map.get("listen_port").unwrap().to_string();
+    if cli.url.is_empty() {
+        cli.url = settings.url.into_vec();
+    }
+    // The environment overrides the settings file, only flags override the environment.
+    if !matches!(
+        matches.value_source("client"),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    ) {
+        if let Some(client) = settings.client {
+            cli.client = vec![client];
         }
     }
+    cli.out_dir = cli.out_dir.take().or(settings.out_dir);
+    cli.template = cli.template.take().or(settings.template);
+    cli.patch = cli.patch.take().or(settings.patch);
+    cli.dns = cli.dns.or(settings.dns);
+    cli.ruleset = cli.ruleset.or(settings.ruleset);
+    cli.block_ads |= settings.block_ads;
+    cli.groups |= settings.groups;
+    cli.region_groups |= settings.region_groups;
+    cli.user_agent = cli.user_agent.take().or(settings.user_agent);
+    cli.proxy = cli.proxy.take().or(settings.proxy);
+    cli.allowed_hosts.extend(settings.allow_host);
+    cli.sing_box_version = cli.sing_box_version.or(settings.sing_box_version);
+    cli.exec = cli.exec.take().or(settings.exec);
+    cli.no_install |= settings.no_install;
+    cli.notify_webhook = cli.notify_webhook.take().or(settings.notify_webhook);
+    cli.telegram_token = cli.telegram_token.take().or(settings.telegram_token);
+    cli.telegram_chat = cli.telegram_chat.take().or(settings.telegram_chat);
+    cli.notify_desktop |= settings.notify_desktop;
+    cli.pre_hook = cli.pre_hook.take().or(settings.pre_hook);
+    cli.post_hook = cli.post_hook.take().or(settings.post_hook);
+    Ok(())
+}
 
-    data.insert(String::from("inbounds"), Value::Array(new_inbound));
+/// Switch into the output directory, keeping input paths pointing at the same files.
+fn enter_out_dir(cli: &mut Args) -> Result<()> {
+    let Some(dir) = cli.out_dir.clone() else {
+        return Ok(());
+    };
+    for path in [
+        &mut cli.template,
+        &mut cli.patch,
+        &mut cli.rename_map,
+        &mut cli.cache_dir,
+        &mut cli.exec,
+        &mut cli.geoip_db,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *path = std::path::absolute(&*path)?;
+    }
+    fs::create_dir_all(&dir)?;
+    env::set_current_dir(&dir)?;
+    info!("✅ Output directory is: {}", env::current_dir()?.display());
+    Ok(())
+}
 
-    let output_config = serde_json::to_string_pretty(&data)?;
+fn sing_box_exec(cli: &Args) -> Result<String> {
+    match &cli.exec {
+        Some(exec) if exec.is_file() => Ok(std::path::absolute(exec)?.display().to_string()),
+        Some(exec) => Err(Error::Install(format!(
+            "sing-box not found at {}.",
+            exec.display()
+        ))),
+        None => find_sing_box(!cli.no_install),
+    }
+}
 
-    let mut file = File::create("config.json")?;
-    file.write_all(output_config.as_bytes())?;
-    println!("✅ Conver successfully, save to: config.json");
-    Ok(controller_info)
+fn notify_options(cli: &Args) -> NotifyOptions {
+    NotifyOptions {
+        webhook: cli.notify_webhook.clone(),
+        telegram_token: cli.telegram_token.clone(),
+        telegram_chat: cli.telegram_chat.clone(),
+        desktop: cli.notify_desktop,
+    }
 }
 
-fn make_external_config(
-    controller: ExternalController,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut output = "External = external, ".to_string();
+fn fetch_options(cli: &Args) -> FetchOptions {
+    FetchOptions {
+        user_agent: cli
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| cli.client[0].default_user_agent().to_string()),
+        headers: cli.headers.clone(),
+        bearer: cli.bearer.clone(),
+        retries: cli.retries,
+        retry_delay: cli.retry_delay,
+        proxy: cli.proxy.clone(),
+        timeout: (cli.timeout > 0).then(|| Duration::from_secs(cli.timeout)),
+        cache_dir: (!cli.no_cache).then(|| cli.cache_dir.clone().unwrap_or_else(default_cache_dir)),
+        force: cli.force,
+        offline_fallback: cli.offline_fallback,
+        max_redirects: cli.max_redirects,
+        https_only: cli.https_only,
+        insecure: cli.insecure,
+        encryption: cli.encryption.clone(),
+    }
+}
 
-    let exec = Command::new("which").arg("sing-box").output()?;
+fn build_converter(cli: &Args) -> Result<Converter> {
+    Ok(Converter {
+        strip: cli.strip.clone(),
+        template: cli.template.as_deref().map(load_template).transpose()?,
+        dns: cli.dns,
+        route: cli.ruleset,
+        block_ads: cli.block_ads,
+        latency: cli.test_latency.then(|| LatencyOptions {
+            timeout: Duration::from_secs(5),
+            max: cli.max_latency.map(Duration::from_millis),
+        }),
+        sort: cli.sort,
+        protocols: cli.protocols.clone(),
+        geoip: cli.geoip.then(|| {
+            cli.geoip_db.clone().unwrap_or_else(|| {
+                cli.cache_dir
+                    .clone()
+                    .unwrap_or_else(default_cache_dir)
+                    .join("Country.mmdb")
+            })
+        }),
+        groups: cli.groups,
+        region_groups: cli.region_groups,
+        target_version: cli
+            .target_version
+            .or(cli.sing_box_version)
+            .or_else(|| detect_sing_box_version(cli.exec.as_deref())),
+        rule_set_dir: cli.download_rule_sets.then(|| cli.rule_set_dir.clone()),
+        fetch: fetch_options(cli),
+        clash_api: cli.clash_api.then(|| ClashApiOptions {
+            address: cli.clash_api_address.clone(),
+            secret: cli.clash_api_secret.clone(),
+            external_ui: cli.external_ui.clone(),
+        }),
+        default_inbound: Some(cli.default_inbound),
+        inbound_tag: cli.inbound_tag.clone(),
+        listen: cli
+            .listen
+            .clone()
+            .or_else(|| cli.listen_ipv6.then(|| String::from("::1"))),
+        port: cli.port,
+        patch: cli
+            .patch
+            .as_deref()
+            .map(load_patch)
+            .transpose()?
+            .unwrap_or_default(),
+        rename_map: cli.rename_map.as_deref().map(RenameMap::load).transpose()?,
+        overrides: match cli.no_override {
+            true => None,
+            false => load_overrides(Path::new(OVERRIDE_FILE))?,
+        },
+    })
+}
 
-    if exec.status.success() {
-        output.push_str(&format!(
-            "exec = \"{}\", ",
-            String::from_utf8_lossy(&exec.stdout).trim()
-        ));
-    } else {
-        println!("✖ sing-box not found, try install...");
-        let install_sing_box = Command::new("brew")
-            .arg("install")
-            .arg("sing-box")
-            .output()?;
-
-        if install_sing_box.status.success() {
-            println!("✅ Successfully installed sing-box");
-            let exec = Command::new("which").arg("sing-box").output()?;
-            output.push_str(&format!(
-                "exec = \"{}\", ",
-                String::from_utf8_lossy(&exec.stdout).trim()
-            ));
+/// Read `input`, or the config in the output directory without one.
+fn read_output_config(cli: &mut Args, input: Option<PathBuf>) -> Result<Config> {
+    let input = match input {
+        Some(input) => std::path::absolute(input)?,
+        None => {
+            enter_out_dir(cli)?;
+            PathBuf::from(CONFIG_FILE)
+        }
+    };
+    Ok(serde_json::from_slice(&fs::read(&input)?)?)
+}
+
+/// The subscription urls of `cli`, checked and logged.
+fn checked_urls(cli: &Args) -> Result<Vec<String>> {
+    if cli.url.is_empty() {
+        return Err(Error::Url(String::from(
+            "No subscription url given, pass --url or set url in the settings file.",
+        )));
+    }
+    let mut sub_urls = Vec::new();
+    for url in &cli.url {
+        let sub_url = check_url(url, &cli.allowed_hosts)?;
+        let shown = if cli.show_url {
+            sub_url.clone()
         } else {
-            return Err(
-                "✖ Failed to install sing-box, please try: brew install sing-box."
-                    .to_string()
-                    .into(),
-            );
+            redact_url(&sub_url)
+        };
+        info!("✅ Target subscription url is: {shown}");
+        sub_urls.push(sub_url);
+    }
+    Ok(sub_urls)
+}
+
+async fn run_fetch(cli: &mut Args, output: Option<PathBuf>) -> Result<()> {
+    let output = output.map(std::path::absolute).transpose()?;
+    enter_out_dir(cli)?;
+    let sub_urls = checked_urls(cli)?;
+    let subscriptions = Subscription::fetch_all(&sub_urls, &fetch_options(cli)).await?;
+    subscriptions
+        .iter()
+        .filter_map(|subscription| subscription.quota)
+        .for_each(|quota| print_quota(&quota));
+    let subscription = Subscription::merge(subscriptions)
+        .ok_or_else(|| Error::Url(String::from("No subscription url given.")))?;
+    info!(
+        "✅ Successfully fetched and parsed {} nodes.",
+        subscription.nodes().len()
+    );
+    let content = serde_json::to_string_pretty(&subscription.config)?;
+    match output {
+        Some(output) => {
+            fs::write(&output, content)?;
+            info!("✅ Saved nodes to: {}", output.display());
+        }
+        None => println!("{content}"),
+    }
+    Ok(())
+}
+
+fn run_validate(cli: &mut Args, input: Option<PathBuf>) -> Result<()> {
+    let input = input.map(std::path::absolute).transpose()?;
+    let config = read_output_config(cli, input.clone())?;
+    // Without --input the output directory is the current one by now.
+    let path = input.unwrap_or_else(|| PathBuf::from(CONFIG_FILE));
+    let problems = validate_config(&config);
+    if !problems.is_empty() {
+        return Err(Error::Validation(format!(
+            "{} is invalid:\n    {}",
+            path.display(),
+            problems.join("\n    ")
+        )));
+    }
+    info!("✅ {} is structurally valid.", path.display());
+    if detect_sing_box_version(cli.exec.as_deref()).is_none() {
+        warn!("✖ sing-box not found, skip sing-box check.");
+        return Ok(());
+    }
+    check_config(&path.display().to_string(), cli.exec.as_deref())?;
+    info!("✅ sing-box check passed.");
+    Ok(())
+}
+
+async fn run_doctor(cli: &Args) -> Result<()> {
+    let options = DoctorOptions {
+        exec: cli.exec.clone(),
+        out_dir: cli.out_dir.clone().unwrap_or_else(|| PathBuf::from(".")),
+        urls: cli.url.clone(),
+        port: cli.port.unwrap_or(cli.default_inbound.port()),
+        timeout: Duration::from_secs(if cli.timeout > 0 {
+            cli.timeout.min(10)
+        } else {
+            10
+        }),
+    };
+    let checks = doctor(&options).await;
+    for check in &checks {
+        if check.ok {
+            info!("✅ {}: {}", check.name, check.detail);
+        } else {
+            warn!("✖ {}: {}", check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                warn!("  → {fix}");
+            }
+        }
+    }
+    print_json(cli, &checks);
+    let failed = checks.iter().filter(|check| !check.ok).count();
+    if failed > 0 {
+        return Err(Error::Validation(format!("{failed} doctor checks failed.")));
+    }
+    info!("✅ Everything looks fine.");
+    Ok(())
+}
+
+fn run_export(cli: &mut Args, input: Option<PathBuf>, base64: bool) -> Result<()> {
+    let config = read_output_config(cli, input)?;
+    let mut links = Vec::new();
+    for node in config
+        .get("outbounds")
+        .and_then(|outbounds| outbounds.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+    {
+        match share_link(&node.outbound) {
+            Some(link) => links.push(link),
+            None => warn!("✖ No share link for {} node {}.", node.protocol, node.tag),
+        }
+    }
+    info!("✅ Exported {} share links.", links.len());
+
+    if cli.format == OutputFormat::Json {
+        print_json(cli, &links);
+    } else if base64 {
+        println!("{}", STANDARD.encode(links.join("\n")));
+    } else {
+        links.iter().for_each(|link| println!("{link}"));
+    }
+    Ok(())
+}
+
+async fn run_speedtest(
+    cli: &mut Args,
+    input: Option<PathBuf>,
+    test_url: String,
+    duration: Duration,
+) -> Result<()> {
+    let config = read_output_config(cli, input)?;
+    let sing_box = sing_box_exec(cli)?;
+    let options = SpeedTestOptions {
+        url: test_url,
+        duration,
+    };
+    let results = speed_test(Path::new(&sing_box), &config, &options).await;
+
+    if cli.format == OutputFormat::Json {
+        print_json(cli, &results);
+        return Ok(());
+    }
+    let width = results
+        .iter()
+        .map(|r| r.tag.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (rank, result) in results.iter().enumerate() {
+        let speed = match (result.speed, &result.error) {
+            (Some(speed), _) => format!("{}/s", format_bytes(speed as u64)),
+            (None, Some(error)) => format!("failed: {error}"),
+            (None, None) => String::from("failed"),
+        };
+        println!("{:>3}  {:<width$}  {speed}", rank + 1, result.tag);
+    }
+    Ok(())
+}
+
+fn print_qr_codes(cli: &Args, config: &Config, report: &mut Report) {
+    if cli.format == OutputFormat::Json {
+        report.warn(String::from("--qr is ignored with --format json."));
+        return;
+    }
+    for node in config
+        .get("outbounds")
+        .and_then(|outbounds| outbounds.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+    {
+        match share_link(&node.outbound).and_then(|link| qr_code(&link)) {
+            Some(code) => println!("{}\n{code}", node.tag),
+            None => report.warn(format!(
+                "No share link for {} node {}.",
+                node.protocol, node.tag
+            )),
+        }
+    }
+}
+
+fn print_summary(summary: &Summary) {
+    let counts = |counts: &BTreeMap<String, usize>| {
+        counts
+            .iter()
+            .map(|(name, count)| format!("{name} {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    info!("✅ Conversion summary:");
+    info!("  {:<10}{}", "Nodes", summary.nodes);
+    info!("  {:<10}{}", "Protocols", counts(&summary.protocols));
+    info!("  {:<10}{}", "Regions", counts(&summary.regions));
+    if !summary.dropped.is_empty() {
+        info!("  {:<10}{}", "Dropped", summary.dropped.len());
+        for dropped in &summary.dropped {
+            info!("  {:<10}{}: {}", "", dropped.tag, dropped.reason);
+        }
+    }
+    if let Some(inbound) = &summary.inbound {
+        info!("  {:<10}{inbound}", "Inbound");
+    }
+    info!("  {:<10}{}", "Size", format_bytes(summary.size));
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    eprint!("{prompt} [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn print_quota(quota: &Quota) {
+    let mut summary = format!(
+        "✅ Traffic used: {} / {}, remaining: {}",
+        format_bytes(quota.used()),
+        format_bytes(quota.total),
+        format_bytes(quota.remaining())
+    );
+    if let Some(expire) = quota.expire {
+        summary.push_str(&format!(", expires: {}", format_timestamp(expire)));
+    }
+    info!("{summary}");
+}
+
+async fn run(mut cli: Args, matches: ArgMatches) -> Result<()> {
+    // Printed before anything else so the script can be redirected as-is.
+    if let Some(Command::Completions { shell }) = cli.command {
+        let mut command = Args::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        return Ok(());
+    }
+    apply_settings(&mut cli, &matches)?;
+    if cli.encrypt {
+        let path = cli.encrypt_key.clone().unwrap_or_else(default_key_path);
+        cli.encryption = Some(Encryption::load_or_create(&path)?);
+    }
+    match cli.command.take() {
+        Some(Command::Profile(command)) => return run_profile(command, &cli),
+        Some(Command::Service(command)) => return run_service(command, &cli),
+        Some(Command::Serve { listen }) => {
+            let state = ServeState {
+                converter: build_converter(&cli)?,
+                allowed_hosts: cli.allowed_hosts.clone(),
+                notify: notify_options(&cli),
+                tags: Mutex::default(),
+                metrics: Arc::default(),
+            };
+            return serve(listen, state).await;
         }
+        Some(Command::Install) => {
+            let path = install_from_github(cli.sing_box_version, &fetch_options(&cli)).await?;
+            info!("✅ Installed sing-box to: {}", path.display());
+            return Ok(());
+        }
+        Some(Command::SelfUpdate { check }) => {
+            match self_update(check, &fetch_options(&cli)).await? {
+                SelfUpdate::UpToDate(version) => info!("✅ Already up to date: {version}"),
+                SelfUpdate::Available(version) => {
+                    info!("✅ Version {version} is available, run `self-update` to install it.")
+                }
+                SelfUpdate::Updated(version) => info!("✅ Updated to version {version}."),
+            }
+            return Ok(());
+        }
+        Some(Command::Dashboard { input }) => {
+            let config = read_output_config(&mut cli, input)?;
+            let clash_api = find_clash_api(&config).ok_or_else(|| {
+                Error::Validation(String::from(
+                    "The config has no clash API, regenerate it with --clash-api.",
+                ))
+            })?;
+            return dashboard(&clash_api).await;
+        }
+        Some(Command::Export { input, base64 }) => return run_export(&mut cli, input, base64),
+        Some(Command::Doctor) => return run_doctor(&cli).await,
+        Some(Command::Fetch { output }) => return run_fetch(&mut cli, output).await,
+        Some(Command::Validate { input }) => return run_validate(&mut cli, input),
+        Some(Command::Speedtest {
+            input,
+            test_url,
+            duration,
+        }) => return run_speedtest(&mut cli, input, test_url, duration).await,
+        Some(Command::Update { all: true, .. }) => return update_all(&cli, &matches).await,
+        Some(Command::Update { name, .. }) => apply_profile(&mut cli, &matches, name.as_deref())?,
+        Some(Command::Convert | Command::Completions { .. }) | None => {}
     }
+    enter_out_dir(&mut cli)?;
 
-    output.push_str(&format!("local-port = {}, ", controller.port));
-    output.push_str("args = \"run\", ");
-    output.push_str("args = \"-c\", ");
-    output.push_str(&format!(
-        "args = \"{}\", ",
-        env::current_dir()?.join("config.json").display()
-    ));
-    output.push_str(&format!("address = {}", controller.address));
-    Ok(output)
+    if let Some(index) = cli.restore {
+        let _lock = lock_output()?;
+        let entry = restore_history(index, cli.encryption.as_ref())?;
+        info!("✅ Restored {CONFIG_FILE} from: {}", entry.display());
+        return Ok(());
+    }
+
+    let sub_urls = checked_urls(&cli)?;
+
+    if let Some(pinned) = cli.sing_box_version {
+        match detect_sing_box_version(cli.exec.as_deref()) {
+            Some(installed) if installed != pinned => warn!(
+                "✖ Installed sing-box {installed} differs from pinned {pinned}, run `install` to sync."
+            ),
+            _ => {}
+        }
+    }
+
+    info!(
+        "✅ Target client type is: {}",
+        cli.client
+            .iter()
+            .map(|client| client.to_possible_value().unwrap().get_name().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    if !cli.daemon {
+        let report = update(&cli, &sub_urls).await?;
+        print_json(&cli, &report);
+        if !report.quota_alerts.is_empty() {
+            let event = Event::QuotaLow {
+                alerts: report.quota_alerts,
+            };
+            notify(&notify_options(&cli), &fetch_options(&cli), &event).await;
+            std::process::exit(exit_code::QUOTA);
+        }
+        return Ok(());
+    }
+    info!(
+        "✅ Running as daemon, updating every {}.",
+        format_age(cli.interval.as_secs())
+    );
+    let notify_options = notify_options(&cli);
+    let fetch = fetch_options(&cli);
+    let mut tags: Option<Vec<String>> = None;
+    let mut alerted = false;
+    let metrics = Arc::new(Metrics::default());
+    if let Some(address) = cli.metrics_listen {
+        let listener = serve_metrics(address, metrics.clone());
+        tokio::spawn(async move {
+            if let Err(e) = listener.await {
+                error!("✖ Serving metrics failed: {e}");
+            }
+        });
+    }
+    loop {
+        info!("✅ Update started at {}", format_timestamp(now()));
+        match update(&cli, &sub_urls).await {
+            Ok(report) => {
+                print_json(&cli, &report);
+                metrics.record_success(report.nodes, &report.quota);
+                if report.output.is_some() {
+                    let event = Event::Updated {
+                        nodes: report.nodes,
+                    };
+                    notify(&notify_options, &fetch, &event).await;
+                }
+                let changed = tags
+                    .as_deref()
+                    .and_then(|old| Event::nodes_changed(old, &report.tags));
+                if let Some(event) = changed {
+                    notify(&notify_options, &fetch, &event).await;
+                }
+                // Once per low period, not on every update.
+                if !report.quota_alerts.is_empty() && !alerted {
+                    let event = Event::QuotaLow {
+                        alerts: report.quota_alerts.clone(),
+                    };
+                    notify(&notify_options, &fetch, &event).await;
+                }
+                alerted = !report.quota_alerts.is_empty();
+                tags = Some(report.tags);
+            }
+            Err(e) => {
+                warn!("✖ Update failed: {e}");
+                metrics.record_failure();
+                print_json(&cli, &error_json(&e));
+                let event = Event::Failed {
+                    error: e.to_string(),
+                };
+                notify(&notify_options, &fetch, &event).await;
+            }
+        }
+        info!(
+            "✅ Next update at {}",
+            format_timestamp(now() + cli.interval.as_secs())
+        );
+        tokio::time::sleep(cli.interval).await;
+    }
 }
 
-fn main() {
-    let cli = Args::parse();
+/// Outcome of one profile of `update --all`.
+#[derive(Debug, Serialize)]
+struct ProfileResult {
+    name: String,
+    exit_code: i32,
+    /// Last error line of a failed update.
+    error: Option<String>,
+    /// Report of the profile with `--format json`.
+    report: Option<serde_json::Value>,
+}
 
-    if let Some(client_name) = cli.client.as_deref() {
-        println!("✅ Target client type is: {client_name}")
+/// The top-level flags given on the command line, rebuilt from `matches` for a child run.
+///
+/// Values from the environment or the settings file reach the child on their own.
+fn command_line_args(matches: &ArgMatches) -> Vec<OsString> {
+    let mut args = Vec::new();
+    for arg in Args::command().get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        match arg.get_action() {
+            ArgAction::SetTrue | ArgAction::SetFalse => {
+                args.push(OsString::from(format!("--{long}")))
+            }
+            ArgAction::Count => {
+                args.extend((0..matches.get_count(id)).map(|_| OsString::from(format!("--{long}"))))
+            }
+            _ => {
+                for value in matches.get_raw(id).into_iter().flatten() {
+                    // `--flag=value` keeps values starting with `-` from being read as flags.
+                    let mut arg = OsString::from(format!("--{long}="));
+                    arg.push(value);
+                    args.push(arg);
+                }
+            }
+        }
     }
+    args
+}
 
-    let sub_url = check_url(&cli.url).unwrap_or_else(|e| {
-        println!("{e}");
-        std::process::exit(1);
+/// Run `update <name>` for every profile in parallel child processes.
+///
+/// Each profile switches into its own output directory, which is process-wide,
+/// so they can't share one process.
+async fn update_all(cli: &Args, matches: &ArgMatches) -> Result<()> {
+    let profiles = Profiles::load(&default_profiles_path())?;
+    if profiles.profiles.is_empty() {
+        return Err(Error::Validation(String::from(
+            "No profiles, add one with `profile add`.",
+        )));
+    }
+    let global = command_line_args(matches);
+    let exe = env::current_exe()?;
+    let runs = profiles.profiles.keys().map(|name| {
+        let mut command = tokio::process::Command::new(&exe);
+        command.args(&global).arg("update").arg(name);
+        async move { (name.clone(), command.output().await) }
     });
-    // TODO: mark real url.
-    println!("✅ Targe subscription url is: {sub_url}");
+    info!("✅ Updating {} profiles.", profiles.profiles.len());
 
-    let data = match fetch_subscription(&sub_url) {
-        Ok(json_resp) => {
-            println!("✅ Successfully fetched and parsed JSON.");
-            json_resp.to_owned()
+    let mut results = Vec::new();
+    for (name, output) in futures_util::future::join_all(runs).await {
+        let output = output?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let exit_code = output.status.code().unwrap_or(exit_code::IO);
+        let mut result = ProfileResult {
+            name,
+            exit_code,
+            error: None,
+            report: None,
+        };
+        if cli.format == OutputFormat::Json {
+            result.report = stdout
+                .lines()
+                .rev()
+                .find_map(|line| serde_json::from_str(line).ok());
+        } else {
+            for line in stdout.lines().chain(stderr.lines()) {
+                println!("[{}] {line}", result.name);
+            }
         }
-        Err(e) => {
-            println!("✖ Error: {e}");
-            std::process::exit(1);
+        if exit_code == exit_code::QUOTA {
+            result.error = Some(String::from("The plan is about to expire or run out."));
+        } else if exit_code != exit_code::SUCCESS {
+            result.error = stderr
+                .lines()
+                .chain(stdout.lines())
+                .rev()
+                .find(|line| line.contains("Error"))
+                .map(|line| line.trim_start_matches("✖ Error: ").to_string());
         }
-    };
+        results.push(result);
+    }
 
-    let controller_info = match save_config(data) {
-        Ok(controller) => {
-            println!("✅ Successfully convert subscription.");
-            controller
+    info!("✅ Update summary:");
+    for result in &results {
+        match &result.error {
+            None if result.exit_code == exit_code::SUCCESS => info!("  ✅ {}", result.name),
+            error => warn!(
+                "  ✖ {} (exit {}): {}",
+                result.name,
+                result.exit_code,
+                error.as_deref().unwrap_or("failed")
+            ),
         }
-        Err(e) => {
-            println!("✖ Error: {e}");
-            std::process::exit(1);
+    }
+    print_json(cli, &results);
+    let failed = results
+        .iter()
+        .filter(|result| result.exit_code != exit_code::SUCCESS)
+        .count();
+    if failed > 0 {
+        return Err(Error::Validation(format!(
+            "{failed} of {} profiles failed to update.",
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Fetch, convert and write the config once.
+async fn update(cli: &Args, sub_urls: &[String]) -> Result<Report> {
+    let mut report = Report::default();
+    if let Some(command) = cli.pre_hook.as_deref() {
+        let output = std::path::absolute(CONFIG_FILE)?;
+        let env = [("OUTPUT", output.display().to_string())];
+        run_hook("pre-hook", command, &env).await?;
+    }
+    let subscriptions = Subscription::fetch_all(sub_urls, &fetch_options(cli)).await?;
+    info!("✅ Successfully fetched and parsed subscription.");
+    for quota in subscriptions
+        .iter()
+        .filter_map(|subscription| subscription.quota)
+    {
+        print_quota(&quota);
+        for alert in quota.alerts(now(), cli.warn_expiry_days, cli.warn_traffic_percent) {
+            report.warn(alert.clone());
+            report.quota_alerts.push(alert);
         }
-    };
+        report.quota.push(quota);
+    }
+    let mut subscription = Subscription::merge(subscriptions)
+        .ok_or_else(|| Error::Url(String::from("No subscription url given.")))?;
+    let sub_url = subscription.url.clone();
+    report.tags = subscription
+        .nodes()
+        .into_iter()
+        .map(|node| node.tag)
+        .collect();
+    report.nodes = report.tags.len();
+    if subscription.not_modified && Path::new(CONFIG_FILE).exists() {
+        info!("✅ Subscription not modified, skip regeneration (use --force to override).");
+        return Ok(report);
+    }
 
-    let external_proxy = match make_external_config(controller_info) {
-        Ok(external_info) => external_info,
-        Err(e) => {
-            println!("✖ Error: {e}");
-            std::process::exit(1);
+    // Held until the configs are written, overlapping runs fail instead of clobbering them.
+    let _lock = lock_output()?;
+    let mut converter = build_converter(cli)?;
+    if let Some(options) = converter.clash_api.as_mut() {
+        if let Some(ui) = cli.dashboard_ui {
+            let dir = env::current_dir()?.join(options.external_ui.as_deref().unwrap_or("ui"));
+            if !install_dashboard(ui, &dir, &converter.fetch).await? {
+                info!("✅ Dashboard already installed in: {}", dir.display());
+            }
+            options.external_ui = Some(dir.display().to_string());
         }
-    };
-    println!(
+        // Keep the previous secret so open dashboards stay logged in.
+        options.secret = options.secret.take().or_else(|| {
+            existing_config()
+                .as_ref()
+                .and_then(find_clash_api)
+                .map(|clash_api| clash_api.secret)
+                .filter(|secret| !secret.is_empty())
+        });
+    }
+    let mut dropped = Vec::new();
+    if cli.pick {
+        let selection = pick_nodes(
+            &subscription.nodes(),
+            converter.groups,
+            converter.region_groups,
+        )?;
+        remove_outbounds(&mut subscription.config, &selection.removed);
+        dropped.extend(
+            selection
+                .removed
+                .iter()
+                .map(|tag| Dropped::new(tag, "deselected in the picker")),
+        );
+        rename_outbounds(&mut subscription.config, &selection.renames);
+        converter.groups = selection.groups;
+        converter.region_groups = selection.region_groups;
+        report.tags = subscription
+            .nodes()
+            .into_iter()
+            .map(|node| node.tag)
+            .collect();
+        report.nodes = report.tags.len();
+        info!("✅ Picked {} nodes.", report.nodes);
+    }
+    let mut conversion = converter.convert(subscription).await?;
+    dropped.append(&mut conversion.dropped);
+    if let Some(template_path) = cli.template.as_deref() {
+        info!("✅ Merged template: {}", template_path.display());
+    }
+    if let Some(preset) = cli.dns {
+        info!(
+            "✅ Applied dns preset: {}",
+            preset.to_possible_value().unwrap().get_name()
+        );
+    }
+    if let Some(preset) = cli.ruleset {
+        info!(
+            "✅ Applied route preset: {}",
+            preset.to_possible_value().unwrap().get_name()
+        );
+    }
+    if !conversion.latency.is_empty() {
+        let reachable = conversion
+            .latency
+            .iter()
+            .filter(|(_, latency)| latency.is_some())
+            .count();
+        info!(
+            "✅ Tested latency of {} nodes, {reachable} reachable.",
+            conversion.latency.len()
+        );
+        if let Some(max) = cli.max_latency {
+            let kept = conversion
+                .latency
+                .iter()
+                .filter(|(_, latency)| latency.is_some_and(|l| l.as_millis() <= u128::from(max)))
+                .count();
+            info!(
+                "✅ Dropped {} nodes slower than {max}ms.",
+                conversion.latency.len() - kept
+            );
+        }
+    }
+    match converter.target_version {
+        Some(version) => info!("✅ Migrated config for sing-box {version}"),
+        None => report.warn(String::from(
+            "Can't detect sing-box version, skip config migration.",
+        )),
+    }
+    if converter.overrides.is_some() {
+        info!("✅ Merged overrides: {OVERRIDE_FILE}");
+    }
+    if let Some(patch_path) = cli.patch.as_deref() {
+        info!("✅ Applied patch: {}", patch_path.display());
+    }
+
+    let owned = existing_config()
+        .as_ref()
+        .and_then(inbound_port)
+        .map(|(_, port)| port);
+    if let Some(conflict) = port_conflict(&conversion.config, owned) {
+        if cli.auto_port {
+            let port = move_inbound_port(&mut conversion.config, owned)?;
+            conversion.controller.port = port.to_string();
+            info!("✅ {conflict} Moved it to port {port}.");
+        } else {
+            report.warn(format!("{conflict} Pass --auto-port to pick a free one."));
+        }
+    }
+
+    let problems = validate_config(&conversion.config);
+    if !problems.is_empty() {
+        return Err(Error::Validation(format!(
+            "Generated config is invalid:\n    {}",
+            problems.join("\n    ")
+        )));
+    }
+
+    if cli.daemon && config_unchanged(&conversion.config)? {
+        info!("✅ Config content unchanged, skip writing.");
+        return Ok(report);
+    }
+
+    if cli.diff || cli.confirm {
+        let changed = match diff_against_existing(&conversion.config) {
+            Ok(None) => {
+                info!("✅ No existing {CONFIG_FILE}, nothing to diff.");
+                true
+            }
+            Ok(Some(lines)) if lines.is_empty() => {
+                info!("✅ No changes against existing {CONFIG_FILE}.");
+                false
+            }
+            Ok(Some(lines)) => {
+                info!("✅ Changes against existing {CONFIG_FILE}:");
+                for line in lines {
+                    info!("  {line}");
+                }
+                true
+            }
+            Err(e) => {
+                report.warn(format!("Can't diff against existing {CONFIG_FILE}: {e}"));
+                true
+            }
+        };
+        if cli.confirm && changed && !confirm("Write the new config?").unwrap_or(false) {
+            return Err(Error::Aborted(CONFIG_FILE.to_string()));
+        }
+    }
+
+    // Check the new config before it replaces the old one.
+    save_config(&conversion.config, |path| {
+        if cli.check {
+            check_config(path, cli.exec.as_deref())?;
+            info!("✅ sing-box check passed.");
+        }
+        Ok(())
+    })?;
+    info!("✅ Successfully convert subscription.");
+    report.output = Some(config_path()?);
+    let mut summary = Summary::new(&conversion.config, dropped);
+    summary.size = fs::metadata(CONFIG_FILE)?.len();
+    print_summary(&summary);
+    report.summary = Some(summary);
+
+    if let Some(dir) = cli.wireguard_dir.as_deref() {
+        let paths = export_wireguard(&conversion.config, dir)?;
+        match paths.is_empty() {
+            true => report.warn(String::from("No WireGuard outbounds to export.")),
+            false => info!(
+                "✅ Exported {} WireGuard configs to: {}",
+                paths.len(),
+                dir.display()
+            ),
+        }
+        report.wireguard = paths
+            .into_iter()
+            .map(std::path::absolute)
+            .collect::<std::io::Result<_>>()?;
+    }
+
+    if cli.qr {
+        print_qr_codes(cli, &conversion.config, &mut report);
+    }
+
+    if cli.history > 0 {
+        record_history(cli.history, cli.encryption.as_ref())?;
+    }
+
+    if cli.reload {
+        match reload_sing_box()? {
+            Reload::Service => info!("✅ Reloaded the sing-box service."),
+            Reload::Signal => info!("✅ Sent SIGHUP to the running sing-box."),
+            Reload::NotRunning => {
+                report.warn(String::from("sing-box is not running, nothing to reload."))
+            }
+        }
+    }
+
+    if cli.git {
+        match git_commit_config(&sub_url)? {
+            true => info!("✅ Committed {CONFIG_FILE} to git."),
+            false => info!("✅ {CONFIG_FILE} unchanged, nothing to commit."),
+        }
+    }
+
+    let external_proxy = external_line(&conversion.controller, &sing_box_exec(cli)?)?;
+    info!(
         "✅ Target surge external config:\n[Proxy]\n{}",
         external_proxy
-    )
+    );
+    if cli.copy {
+        match copy_to_clipboard(&external_proxy) {
+            Ok(tool) => info!("✅ Copied the [Proxy] line to the clipboard with {tool}."),
+            Err(e) => report.warn(format!("Can't copy to the clipboard: {e}")),
+        }
+    }
+
+    report.outputs = save_client_configs(&conversion.config, &cli.client, &external_proxy)?;
+    report.surge = Some(external_proxy);
+    report.controller = Some(Controller {
+        address: conversion.controller.address,
+        port: conversion.controller.port,
+    });
+
+    if let Some(clash_api) = conversion.clash_api {
+        info!(
+            "✅ Clash API listening on: http://{}, secret: {}",
+            clash_api.address, clash_api.secret
+        );
+        let dashboard = clash_api.dashboard_url();
+        info!("✅ Dashboard: {dashboard}");
+        report.dashboard = Some(dashboard);
+        report.clash_api = Some(clash_api.address);
+    }
+
+    if let Some(command) = cli.post_hook.as_deref() {
+        let env = [
+            ("OUTPUT", config_path()?.display().to_string()),
+            ("NODES", report.nodes.to_string()),
+            ("SUMMARY", serde_json::to_string(&report.summary)?),
+            ("SURGE", report.surge.clone().unwrap_or_default()),
+        ];
+        if let Err(e) = run_hook("post-hook", command, &env).await {
+            report.warn(e.to_string());
+        }
+    }
+    Ok(report)
+}
+
+fn error_json(e: &Error) -> serde_json::Value {
+    serde_json::json!({ "error": e.to_string(), "exit_code": e.exit_code() })
+}
+
+/// Drops the ✅/✖ prefixes from log lines for `--plain`.
+struct PlainWriter(Box<dyn Write>);
+
+impl Write for PlainWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf)
+            .replace("✅ ", "")
+            .replace("✖ ", "");
+        self.0.write_all(text.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn init_logging(cli: &Args) {
+    let level = match (cli.quiet, cli.verbose) {
+        (true, _) => LevelFilter::WARN,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    // Logs move to stderr when stdout carries the JSON result or exported links.
+    let json = cli.format == OutputFormat::Json
+        || matches!(
+            cli.command,
+            Some(Command::Export { .. } | Command::Fetch { output: None })
+        );
+    let terminal = if json {
+        std::io::stderr().is_terminal()
+    } else {
+        std::io::stdout().is_terminal()
+    };
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let plain = cli.plain;
+    // Plain lines at the default level, the level and source once debugging.
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(move || -> Box<dyn Write> {
+            let writer: Box<dyn Write> = if json {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            };
+            if plain {
+                Box::new(PlainWriter(writer))
+            } else {
+                writer
+            }
+        })
+        .with_ansi(terminal && !no_color && !cli.no_color && !plain)
+        .without_time()
+        .with_level(level > LevelFilter::INFO)
+        .with_target(level > LevelFilter::INFO);
+    // Dependencies only get to report their own problems.
+    let filter = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+        .with_default(level.min(LevelFilter::WARN));
+    tracing_subscriber::registry()
+        .with(layer)
+        .with(filter)
+        .init();
+}
+
+#[tokio::main]
+async fn main() {
+    // clap exits with 2 on usage errors, which is taken by invalid urls.
+    let usage_error = |e: clap::Error| -> ! {
+        if !e.use_stderr() {
+            e.exit();
+        }
+        let _ = e.print();
+        std::process::exit(exit_code::USAGE);
+    };
+    let matches = Args::command()
+        .try_get_matches()
+        .unwrap_or_else(|e| usage_error(e));
+    let cli = Args::from_arg_matches(&matches).unwrap_or_else(|e| usage_error(e));
+    init_logging(&cli);
+
+    let format = cli.format;
+    if let Err(e) = run(cli, matches).await {
+        error!("✖ Error: {e}");
+        if format == OutputFormat::Json {
+            println!("{}", error_json(&e));
+        }
+        std::process::exit(e.exit_code());
+    }
 }
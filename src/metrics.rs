@@ -0,0 +1,130 @@
+//! Prometheus metrics for daemon and serve mode.
+
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::State, http::header::CONTENT_TYPE, response::IntoResponse, routing::get, Router,
+};
+use tracing::info;
+
+use crate::{
+    source::{cache::now, Quota},
+    Result,
+};
+
+#[derive(Debug, Default)]
+struct Values {
+    updates: u64,
+    failures: u64,
+    last_update: Option<u64>,
+    last_success: Option<u64>,
+    nodes: Option<usize>,
+    quota_remaining: Option<u64>,
+    quota_expire: Option<u64>,
+}
+
+/// Counters and gauges about subscription refreshes, shared with the `/metrics` handler.
+#[derive(Debug, Default)]
+pub struct Metrics(Mutex<Values>);
+
+impl Metrics {
+    /// A successful update or conversion of a subscription with `nodes` nodes.
+    pub fn record_success(&self, nodes: usize, quotas: &[Quota]) {
+        let mut values = self.0.lock().unwrap();
+        values.updates += 1;
+        values.last_update = Some(now());
+        values.last_success = values.last_update;
+        values.nodes = Some(nodes);
+        if !quotas.is_empty() {
+            values.quota_remaining = Some(quotas.iter().map(Quota::remaining).sum());
+            values.quota_expire = quotas.iter().filter_map(|quota| quota.expire).min();
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let mut values = self.0.lock().unwrap();
+        values.updates += 1;
+        values.failures += 1;
+        values.last_update = Some(now());
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let values = self.0.lock().unwrap();
+        let mut output = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: Option<u64>| {
+            let Some(value) = value else {
+                return;
+            };
+            let name = format!("external_convertor_{name}");
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} {kind}");
+            let _ = writeln!(output, "{name} {value}");
+        };
+        metric(
+            "updates_total",
+            "counter",
+            "Subscription updates attempted.",
+            Some(values.updates),
+        );
+        metric(
+            "fetch_failures_total",
+            "counter",
+            "Subscription updates that failed.",
+            Some(values.failures),
+        );
+        metric(
+            "last_update_timestamp_seconds",
+            "gauge",
+            "Unix time of the last update attempt.",
+            values.last_update,
+        );
+        metric(
+            "last_success_timestamp_seconds",
+            "gauge",
+            "Unix time of the last successful update.",
+            values.last_success,
+        );
+        metric(
+            "nodes",
+            "gauge",
+            "Nodes in the last successful update.",
+            values.nodes.map(|nodes| nodes as u64),
+        );
+        metric(
+            "quota_remaining_bytes",
+            "gauge",
+            "Traffic left on the plan, summed over subscriptions.",
+            values.quota_remaining,
+        );
+        metric(
+            "quota_expiry_timestamp_seconds",
+            "gauge",
+            "Unix time the first plan expires.",
+            values.quota_expire,
+        );
+        output
+    }
+}
+
+pub async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+/// Serve `GET /metrics` on `address` until the process is stopped.
+pub async fn serve_metrics(address: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics);
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("✅ Serving metrics on: http://{address}/metrics");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
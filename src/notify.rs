@@ -0,0 +1,158 @@
+use std::process::Command;
+
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::{
+    source::{build_client, redact_error, FetchOptions},
+    Result,
+};
+
+const TITLE: &str = "External Convertor";
+
+/// Something worth telling the user about while running unattended.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Updated {
+        nodes: usize,
+    },
+    Failed {
+        error: String,
+    },
+    NodesChanged {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// The plan is about to expire or run out of traffic.
+    QuotaLow {
+        alerts: Vec<String>,
+    },
+}
+
+impl Event {
+    /// Compare node tags of two runs, `None` when the set is the same.
+    pub fn nodes_changed(old: &[String], new: &[String]) -> Option<Event> {
+        let added: Vec<String> = new
+            .iter()
+            .filter(|tag| !old.contains(tag))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = old
+            .iter()
+            .filter(|tag| !new.contains(tag))
+            .cloned()
+            .collect();
+        (!added.is_empty() || !removed.is_empty()).then_some(Event::NodesChanged { added, removed })
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Updated { .. } => "updated",
+            Event::Failed { .. } => "failed",
+            Event::NodesChanged { .. } => "nodes_changed",
+            Event::QuotaLow { .. } => "quota_low",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Event::Updated { nodes } => format!("Config updated with {nodes} nodes."),
+            Event::Failed { error } => format!("Update failed: {error}"),
+            Event::NodesChanged { added, removed } => {
+                let mut message = String::from("Nodes changed.");
+                if !added.is_empty() {
+                    message.push_str(&format!("\nAdded: {}", added.join(", ")));
+                }
+                if !removed.is_empty() {
+                    message.push_str(&format!("\nRemoved: {}", removed.join(", ")));
+                }
+                message
+            }
+            Event::QuotaLow { alerts } => alerts.join("\n"),
+        }
+    }
+}
+
+/// Where notifications are sent, every configured channel gets each event.
+#[derive(Debug, Clone, Default)]
+pub struct NotifyOptions {
+    /// POSTed `{"event": ..., "message": ...}` as JSON.
+    pub webhook: Option<String>,
+    pub telegram_token: Option<String>,
+    pub telegram_chat: Option<String>,
+    /// Show a desktop notification with osascript or notify-send.
+    pub desktop: bool,
+}
+
+impl NotifyOptions {
+    pub fn is_empty(&self) -> bool {
+        self.webhook.is_none() && self.telegram_token.is_none() && !self.desktop
+    }
+}
+
+fn desktop_notification(message: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"{TITLE}\"",
+            escape(message)
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg(TITLE).arg(message);
+        command
+    };
+    command.output()?;
+    Ok(())
+}
+
+/// Send `event` to every configured channel, failures are only logged.
+pub async fn notify(options: &NotifyOptions, fetch: &FetchOptions, event: &Event) {
+    if options.is_empty() {
+        return;
+    }
+    debug!("Notifying {}: {}", event.kind(), event.message());
+    let client = match build_client(fetch, HeaderMap::new()) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("✖ Can't send notification: {e}");
+            return;
+        }
+    };
+
+    let mut requests = Vec::new();
+    if let Some(webhook) = &options.webhook {
+        requests.push((
+            "webhook",
+            client
+                .post(webhook)
+                .json(&json!({ "event": event.kind(), "message": event.message() })),
+        ));
+    }
+    if let (Some(token), Some(chat)) = (&options.telegram_token, &options.telegram_chat) {
+        requests.push((
+            "Telegram",
+            client
+                .post(format!("https://api.telegram.org/bot{token}/sendMessage"))
+                .json(&json!({ "chat_id": chat, "text": format!("{TITLE}: {}", event.message()) })),
+        ));
+    }
+    for (channel, request) in requests {
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => warn!(
+                "✖ {channel} notification failed: HTTP {}",
+                response.status()
+            ),
+            Err(e) => warn!("✖ {channel} notification failed: {}", redact_error(&e)),
+        }
+    }
+    if options.desktop {
+        if let Err(e) = desktop_notification(&event.message()) {
+            warn!("✖ Desktop notification failed: {e}");
+        }
+    }
+}
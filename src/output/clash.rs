@@ -0,0 +1,258 @@
+use serde_json::{json, Map, Value};
+
+use super::{enabled, str};
+use crate::{
+    convert::{inbound_port, Node},
+    Config, Error, Result,
+};
+
+pub const CLASH_FILE: &str = "config.yaml";
+
+/// Copy the sing-box TLS block into Clash `tls`, `servername`/`sni` and friends.
+fn tls(outbound: &Value, proxy: &mut Map<String, Value>, sni_key: &str) {
+    if !enabled(outbound, "/tls/enabled") {
+        return;
+    }
+    proxy.insert(String::from("tls"), json!(true));
+    if let Some(sni) = str(outbound, "/tls/server_name") {
+        proxy.insert(String::from(sni_key), json!(sni));
+    }
+    if enabled(outbound, "/tls/insecure") {
+        proxy.insert(String::from("skip-cert-verify"), json!(true));
+    }
+    if let Some(alpn) = outbound.pointer("/tls/alpn") {
+        proxy.insert(String::from("alpn"), alpn.clone());
+    }
+    if let Some(fingerprint) = str(outbound, "/tls/utls/fingerprint") {
+        proxy.insert(String::from("client-fingerprint"), json!(fingerprint));
+    }
+    if enabled(outbound, "/tls/reality/enabled") {
+        proxy.insert(
+            String::from("reality-opts"),
+            json!({
+                "public-key": str(outbound, "/tls/reality/public_key"),
+                "short-id": str(outbound, "/tls/reality/short_id").unwrap_or_default(),
+            }),
+        );
+    }
+}
+
+/// Copy the sing-box transport into Clash `network` and `<network>-opts`.
+fn transport(outbound: &Value, proxy: &mut Map<String, Value>) {
+    let Some(network) = str(outbound, "/transport/type") else {
+        return;
+    };
+    match network {
+        "ws" | "httpupgrade" => {
+            let mut options = Map::new();
+            if let Some(path) = str(outbound, "/transport/path") {
+                options.insert(String::from("path"), json!(path));
+            }
+            let host = str(outbound, "/transport/headers/Host")
+                .or_else(|| str(outbound, "/transport/host"));
+            if let Some(host) = host {
+                options.insert(String::from("headers"), json!({ "Host": host }));
+            }
+            if network == "httpupgrade" {
+                options.insert(String::from("v2ray-http-upgrade"), json!(true));
+            }
+            proxy.insert(String::from("network"), json!("ws"));
+            proxy.insert(String::from("ws-opts"), Value::Object(options));
+        }
+        "http" => {
+            let mut options = Map::new();
+            if let Some(path) = str(outbound, "/transport/path") {
+                options.insert(String::from("path"), json!(path));
+            }
+            if let Some(host) = outbound.pointer("/transport/host") {
+                options.insert(String::from("host"), host.clone());
+            }
+            proxy.insert(String::from("network"), json!("h2"));
+            proxy.insert(String::from("h2-opts"), Value::Object(options));
+        }
+        "grpc" => {
+            proxy.insert(String::from("network"), json!("grpc"));
+            proxy.insert(
+                String::from("grpc-opts"),
+                json!({ "grpc-service-name": str(outbound, "/transport/service_name").unwrap_or_default() }),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Clash (mihomo) proxy for a node outbound, `None` for unsupported types.
+pub fn clash_proxy(outbound: &Value) -> Option<Value> {
+    let (kind, sni_key) = match str(outbound, "/type")? {
+        "shadowsocks" => ("ss", "sni"),
+        "vmess" => ("vmess", "servername"),
+        "vless" => ("vless", "servername"),
+        "trojan" => ("trojan", "sni"),
+        "hysteria2" => ("hysteria2", "sni"),
+        "tuic" => ("tuic", "sni"),
+        "socks" => ("socks5", "sni"),
+        "http" => ("http", "sni"),
+        _ => return None,
+    };
+    let mut proxy = Map::new();
+    proxy.insert(String::from("name"), json!(str(outbound, "/tag")?));
+    proxy.insert(String::from("type"), json!(kind));
+    proxy.insert(String::from("server"), json!(str(outbound, "/server")?));
+    proxy.insert(String::from("port"), outbound.get("server_port")?.clone());
+    let mut copy = |from: &str, to: &str| {
+        if let Some(value) = outbound.get(from).filter(|v| !v.is_null()) {
+            proxy.insert(String::from(to), value.clone());
+        }
+    };
+    match kind {
+        "ss" => {
+            copy("method", "cipher");
+            copy("password", "password");
+        }
+        "vmess" => {
+            copy("uuid", "uuid");
+            copy("alter_id", "alterId");
+            copy("security", "cipher");
+        }
+        "vless" => {
+            copy("uuid", "uuid");
+            copy("flow", "flow");
+        }
+        "tuic" => {
+            copy("uuid", "uuid");
+            copy("password", "password");
+            copy("congestion_control", "congestion-controller");
+            copy("udp_relay_mode", "udp-relay-mode");
+        }
+        "hysteria2" => {
+            copy("password", "password");
+            copy("up_mbps", "up");
+            copy("down_mbps", "down");
+        }
+        "socks5" | "http" => {
+            copy("username", "username");
+            copy("password", "password");
+        }
+        _ => copy("password", "password"),
+    }
+    if kind == "ss" {
+        if let Some(plugin) = str(outbound, "/plugin") {
+            let options: Map<String, Value> = str(outbound, "/plugin_opts")
+                .unwrap_or_default()
+                .split(';')
+                .filter_map(|option| {
+                    let (key, value) = option.split_once('=').unwrap_or((option, "true"));
+                    (!key.is_empty()).then(|| (key.to_string(), json!(value)))
+                })
+                .collect();
+            let (plugin, options) = match plugin {
+                "obfs-local" => (
+                    "obfs",
+                    json!({ "mode": options.get("obfs"), "host": options.get("obfs-host") }),
+                ),
+                plugin => (plugin, Value::Object(options)),
+            };
+            proxy.insert(String::from("plugin"), json!(plugin));
+            proxy.insert(String::from("plugin-opts"), options);
+        }
+    }
+    if kind == "hysteria2" {
+        if let Some(obfs) = str(outbound, "/obfs/type") {
+            proxy.insert(String::from("obfs"), json!(obfs));
+            proxy.insert(
+                String::from("obfs-password"),
+                json!(str(outbound, "/obfs/password").unwrap_or_default()),
+            );
+        }
+    }
+    tls(outbound, &mut proxy, sni_key);
+    transport(outbound, &mut proxy);
+    Some(Value::Object(proxy))
+}
+
+/// Clash group type for a sing-box group outbound.
+fn group_type(outbound: &Value) -> Option<&'static str> {
+    match str(outbound, "/type")? {
+        "selector" => Some("select"),
+        "urltest" => Some("url-test"),
+        _ => None,
+    }
+}
+
+/// A Clash config with the nodes and groups of a sing-box config.
+///
+/// Members Clash can't express are dropped, `direct` becomes `DIRECT` and
+/// everything not matched goes through the first group.
+pub fn clash_config(data: &Config) -> Result<String> {
+    let outbounds: &[Value] = data
+        .get("outbounds")
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice);
+    let proxies: Vec<Value> = outbounds
+        .iter()
+        .filter_map(Node::from_outbound)
+        .filter_map(|node| clash_proxy(&node.outbound))
+        .collect();
+    if proxies.is_empty() {
+        return Err(Error::Validation(String::from(
+            "No nodes Clash supports, skip writing its config.",
+        )));
+    }
+    let names: Vec<&str> = proxies
+        .iter()
+        .filter_map(|proxy| proxy.get("name")?.as_str())
+        .collect();
+    let group_names: Vec<&str> = outbounds
+        .iter()
+        .filter(|outbound| group_type(outbound).is_some())
+        .filter_map(|outbound| str(outbound, "/tag"))
+        .collect();
+
+    let mut groups: Vec<Value> = outbounds
+        .iter()
+        .filter_map(|outbound| {
+            let kind = group_type(outbound)?;
+            let members: Vec<&str> = outbound
+                .get("outbounds")?
+                .as_array()?
+                .iter()
+                .filter_map(Value::as_str)
+                .filter_map(|member| match member {
+                    "direct" => Some("DIRECT"),
+                    member if names.contains(&member) || group_names.contains(&member) => {
+                        Some(member)
+                    }
+                    _ => None,
+                })
+                .collect();
+            if members.is_empty() {
+                return None;
+            }
+            let mut group =
+                json!({ "name": str(outbound, "/tag")?, "type": kind, "proxies": members });
+            if kind == "url-test" {
+                group["url"] =
+                    json!(str(outbound, "/url").unwrap_or("https://www.gstatic.com/generate_204"));
+                group["interval"] = json!(300);
+            }
+            Some(group)
+        })
+        .collect();
+    if groups.is_empty() {
+        let mut members = names.clone();
+        members.push("DIRECT");
+        groups.push(json!({ "name": "Proxy", "type": "select", "proxies": members }));
+    }
+    let main_group = groups[0]["name"].as_str().unwrap_or("Proxy").to_string();
+
+    let config = json!({
+        "mixed-port": inbound_port(data).map_or(7890, |(_, port)| port),
+        "allow-lan": false,
+        "mode": "rule",
+        "log-level": "info",
+        "proxies": proxies,
+        "proxy-groups": groups,
+        "rules": [format!("MATCH,{main_group}")],
+    });
+    serde_yaml::to_string(&config).map_err(|e| Error::Validation(format!("Can't write YAML: {e}")))
+}
@@ -0,0 +1,49 @@
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+use crate::{Error, Result};
+
+/// Clipboard tools tried in order on each platform.
+fn clipboard_commands() -> &'static [&'static [&'static str]] {
+    if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else if cfg!(windows) {
+        &[&["clip"]]
+    } else {
+        &[
+            &["wl-copy"],
+            &["xclip", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+        ]
+    }
+}
+
+/// Put `text` on the system clipboard, returning the tool that did it.
+pub fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    for command in clipboard_commands() {
+        let mut child = match Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::Io(format!("Can't run {}: {e}", command[0]))),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::Io(format!("{} exited with {status}", command[0])));
+        }
+        return Ok(command[0]);
+    }
+    Err(Error::Io(String::from(
+        "No clipboard tool found, install wl-copy, xclip or xsel.",
+    )))
+}
@@ -0,0 +1,91 @@
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use super::CONFIG_FILE;
+use crate::{Config, Result};
+
+fn tagged_entries(data: &Config, key: &str) -> Vec<(String, Value)> {
+    data.get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|entry| {
+            let tag = entry
+                .get("tag")
+                .and_then(Value::as_str)
+                .unwrap_or("<untagged>");
+            (tag.to_string(), entry.clone())
+        })
+        .collect()
+}
+
+/// Human readable changes between two configs, one line per change.
+pub fn config_diff(old: &Config, new: &Config) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for key in ["inbounds", "outbounds"] {
+        let old_entries = tagged_entries(old, key);
+        let new_entries = tagged_entries(new, key);
+        for (tag, entry) in &new_entries {
+            match old_entries.iter().find(|(old_tag, _)| old_tag == tag) {
+                None => lines.push(format!("+ {key}: {tag}")),
+                Some((_, old_entry)) if old_entry != entry => {
+                    let mut changed: Vec<&str> = Vec::new();
+                    if let (Some(old_entry), Some(entry)) =
+                        (old_entry.as_object(), entry.as_object())
+                    {
+                        for field in old_entry.keys().chain(entry.keys()) {
+                            if old_entry.get(field) != entry.get(field)
+                                && !changed.contains(&field.as_str())
+                            {
+                                changed.push(field);
+                            }
+                        }
+                    }
+                    lines.push(format!("~ {key}: {tag} ({})", changed.join(", ")));
+                    for port in ["listen_port", "server_port"] {
+                        if changed.contains(&port) {
+                            lines.push(format!(
+                                "    {port}: {} -> {}",
+                                old_entry.get(port).unwrap_or(&Value::Null),
+                                entry.get(port).unwrap_or(&Value::Null)
+                            ));
+                        }
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        for (tag, _) in &old_entries {
+            if !new_entries.iter().any(|(new_tag, _)| new_tag == tag) {
+                lines.push(format!("- {key}: {tag}"));
+            }
+        }
+    }
+
+    let mut sections: Vec<&String> = old.keys().chain(new.keys()).collect();
+    sections.sort();
+    sections.dedup();
+    for section in sections {
+        if section == "inbounds" || section == "outbounds" {
+            continue;
+        }
+        match (old.get(section), new.get(section)) {
+            (None, Some(_)) => lines.push(format!("+ {section}")),
+            (Some(_), None) => lines.push(format!("- {section}")),
+            (Some(old), Some(new)) if old != new => lines.push(format!("~ {section}")),
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Diff `data` against the config on disk, `None` when there is no existing config.
+pub fn diff_against_existing(data: &Config) -> Result<Option<Vec<String>>> {
+    if !Path::new(CONFIG_FILE).exists() {
+        return Ok(None);
+    }
+    let old: Config = serde_json::from_str(&fs::read_to_string(CONFIG_FILE)?)?;
+    Ok(Some(config_diff(&old, data)))
+}
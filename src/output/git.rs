@@ -0,0 +1,51 @@
+use std::{
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use url::Url;
+
+use super::{format_timestamp, CONFIG_FILE};
+use crate::{Error, Result};
+
+pub fn git_commit_config(sub_url: &str) -> Result<bool> {
+    let git = |args: &[&str]| Command::new("git").args(args).output();
+
+    let inside = git(&["rev-parse", "--is-inside-work-tree"])?;
+    if !inside.status.success() {
+        return Err(Error::Io(String::from(
+            "Output directory is not a git repository.",
+        )));
+    }
+    if !git(&["add", CONFIG_FILE])?.status.success() {
+        return Err(Error::Io(format!("Failed to stage {CONFIG_FILE}.")));
+    }
+    if git(&["diff", "--cached", "--quiet", "--", CONFIG_FILE])?
+        .status
+        .success()
+    {
+        return Ok(false);
+    }
+
+    let host = Url::parse(sub_url)
+        .map_err(|e| Error::Url(format!("URL parse failed: {e}")))?
+        .host_str()
+        .unwrap_or_default()
+        .to_string();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let message = format!(
+        "Update {CONFIG_FILE} from {host} at {}",
+        format_timestamp(timestamp)
+    );
+    let commit = git(&["commit", "-m", &message, "--", CONFIG_FILE])?;
+    if !commit.status.success() {
+        return Err(Error::Io(format!(
+            "Failed to commit {CONFIG_FILE}: {}",
+            String::from_utf8_lossy(&commit.stderr).trim()
+        )));
+    }
+    Ok(true)
+}
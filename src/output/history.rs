@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing::info;
+
+use super::{replace_config, CONFIG_FILE, HISTORY_DIR};
+use crate::{
+    encryption::{open, Encryption},
+    Error, Result,
+};
+
+/// `<time>` or `<time>-<n>`, the counter telling apart configs recorded in the same second.
+fn parse_stamp(stamp: &str) -> Option<(u64, u32)> {
+    match stamp.split_once('-') {
+        Some((timestamp, counter)) => Some((timestamp.parse().ok()?, counter.parse().ok()?)),
+        None => Some((stamp.parse().ok()?, 0)),
+    }
+}
+
+/// History entries sorted from newest to oldest.
+fn history_entries() -> Result<Vec<PathBuf>> {
+    if !Path::new(HISTORY_DIR).exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<((u64, u32), PathBuf)> = fs::read_dir(HISTORY_DIR)?
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let name = name.strip_suffix(".age").unwrap_or(&name);
+            let stamp = name.strip_prefix("config-")?.strip_suffix(".json")?;
+            Some((parse_stamp(stamp)?, entry.path()))
+        })
+        .collect();
+    entries.sort_by_key(|(stamp, _)| std::cmp::Reverse(*stamp));
+    Ok(entries.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Copy the config into the history as `config-<time>.json`, encrypted to
+/// `config-<time>.json.age` when a key is given.
+pub fn record_history(keep: usize, encryption: Option<&Encryption>) -> Result<()> {
+    fs::create_dir_all(HISTORY_DIR)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    // A second run within the same second must not overwrite the first one's entry.
+    let mut path = Path::new(HISTORY_DIR).join(format!("config-{timestamp}.json"));
+    let mut counter = 0;
+    while path.exists() || path.with_extension("json.age").exists() {
+        counter += 1;
+        path = Path::new(HISTORY_DIR).join(format!("config-{timestamp}-{counter}.json"));
+    }
+    let path = match encryption {
+        Some(encryption) => {
+            let path = path.with_extension("json.age");
+            fs::write(&path, encryption.encrypt(&fs::read(CONFIG_FILE)?)?)?;
+            path
+        }
+        None => {
+            fs::copy(CONFIG_FILE, &path)?;
+            path
+        }
+    };
+
+    for stale in history_entries()?.into_iter().skip(keep) {
+        fs::remove_file(stale)?;
+    }
+    info!("✅ Recorded config history: {}", path.display());
+    Ok(())
+}
+
+pub fn restore_history(index: usize, encryption: Option<&Encryption>) -> Result<PathBuf> {
+    let entries = history_entries()?;
+    let Some(entry) = index.checked_sub(1).and_then(|i| entries.get(i)) else {
+        let mut message = format!("No history entry {index}, available:");
+        for (i, entry) in entries.iter().enumerate() {
+            message.push_str(&format!("\n    {}: {}", i + 1, entry.display()));
+        }
+        return Err(Error::Validation(message));
+    };
+    replace_config(&open(encryption, fs::read(entry)?)?, |_| Ok(()))?;
+    Ok(entry.clone())
+}
@@ -0,0 +1,241 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use qrcode::{render::unicode::Dense1x2, QrCode};
+use serde_json::{json, Value};
+
+use super::str;
+use crate::convert::socket_address;
+
+/// Everything but RFC 3986 unreserved characters.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn encode(text: &str) -> String {
+    utf8_percent_encode(text, COMPONENT).to_string()
+}
+
+/// Query parameters in insertion order, skipping missing values.
+#[derive(Default)]
+struct Params(Vec<(&'static str, String)>);
+
+impl Params {
+    fn add(&mut self, name: &'static str, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            self.0.push((name, value.to_string()));
+        }
+    }
+
+    fn render(&self) -> String {
+        if self.0.is_empty() {
+            return String::new();
+        }
+        let pairs: Vec<String> = self
+            .0
+            .iter()
+            .map(|(name, value)| format!("{name}={}", encode(value)))
+            .collect();
+        format!("?{}", pairs.join("&"))
+    }
+}
+
+/// `host:port`, bracketing IPv6 addresses.
+fn authority(outbound: &Value) -> Option<String> {
+    let server = str(outbound, "/server")?;
+    let port = outbound.get("server_port")?.as_u64()?;
+    Some(socket_address(server, port))
+}
+
+fn tls_params(outbound: &Value, params: &mut Params) {
+    if outbound.pointer("/tls/enabled") != Some(&Value::Bool(true)) {
+        return;
+    }
+    params.add("sni", str(outbound, "/tls/server_name"));
+    let alpn = outbound
+        .pointer("/tls/alpn")
+        .and_then(Value::as_array)
+        .map(|alpn| {
+            alpn.iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+    params.add("alpn", alpn.filter(|alpn| !alpn.is_empty()));
+    params.add("fp", str(outbound, "/tls/utls/fingerprint"));
+    if outbound.pointer("/tls/insecure") == Some(&Value::Bool(true)) {
+        params.add("insecure", Some(1));
+    }
+}
+
+fn transport_params(outbound: &Value, params: &mut Params) {
+    let network = str(outbound, "/transport/type").unwrap_or("tcp");
+    params.add("type", Some(network));
+    params.add("path", str(outbound, "/transport/path"));
+    let host = str(outbound, "/transport/headers/Host")
+        .map(String::from)
+        .or_else(|| str(outbound, "/transport/host").map(String::from))
+        .or_else(|| {
+            outbound
+                .pointer("/transport/host")
+                .and_then(Value::as_array)
+                .map(|hosts| {
+                    hosts
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+        });
+    params.add("host", host);
+    params.add("serviceName", str(outbound, "/transport/service_name"));
+}
+
+fn link(scheme: &str, userinfo: &str, outbound: &Value, params: &Params) -> Option<String> {
+    let tag = encode(str(outbound, "/tag").unwrap_or_default());
+    Some(format!(
+        "{scheme}://{userinfo}@{}{}#{tag}",
+        authority(outbound)?,
+        params.render()
+    ))
+}
+
+fn vmess(outbound: &Value) -> Option<String> {
+    let tls = outbound.pointer("/tls/enabled") == Some(&Value::Bool(true));
+    let host =
+        str(outbound, "/transport/headers/Host").or_else(|| str(outbound, "/transport/host"));
+    let path =
+        str(outbound, "/transport/path").or_else(|| str(outbound, "/transport/service_name"));
+    let info = json!({
+        "v": "2",
+        "ps": str(outbound, "/tag").unwrap_or_default(),
+        "add": str(outbound, "/server")?,
+        "port": outbound.get("server_port")?.as_u64()?.to_string(),
+        "id": str(outbound, "/uuid")?,
+        "aid": outbound.get("alter_id").and_then(Value::as_u64).unwrap_or_default().to_string(),
+        "scy": str(outbound, "/security").unwrap_or("auto"),
+        "net": str(outbound, "/transport/type").unwrap_or("tcp"),
+        "type": "none",
+        "host": host.unwrap_or_default(),
+        "path": path.unwrap_or_default(),
+        "tls": if tls { "tls" } else { "" },
+        "sni": str(outbound, "/tls/server_name").unwrap_or_default(),
+        "fp": str(outbound, "/tls/utls/fingerprint").unwrap_or_default(),
+    });
+    Some(format!("vmess://{}", STANDARD.encode(info.to_string())))
+}
+
+fn vless(outbound: &Value) -> Option<String> {
+    let mut params = Params::default();
+    params.add("encryption", Some("none"));
+    params.add("flow", str(outbound, "/flow"));
+    let reality = outbound.pointer("/tls/reality/enabled") == Some(&Value::Bool(true));
+    let tls = outbound.pointer("/tls/enabled") == Some(&Value::Bool(true));
+    params.add(
+        "security",
+        match (reality, tls) {
+            (true, _) => Some("reality"),
+            (false, true) => Some("tls"),
+            (false, false) => None,
+        },
+    );
+    tls_params(outbound, &mut params);
+    if reality {
+        params.add("pbk", str(outbound, "/tls/reality/public_key"));
+        params.add("sid", str(outbound, "/tls/reality/short_id"));
+    }
+    transport_params(outbound, &mut params);
+    link("vless", &encode(str(outbound, "/uuid")?), outbound, &params)
+}
+
+fn trojan(outbound: &Value) -> Option<String> {
+    let mut params = Params::default();
+    if outbound.pointer("/tls/enabled") != Some(&Value::Bool(true)) {
+        params.add("security", Some("none"));
+    }
+    tls_params(outbound, &mut params);
+    transport_params(outbound, &mut params);
+    link(
+        "trojan",
+        &encode(str(outbound, "/password")?),
+        outbound,
+        &params,
+    )
+}
+
+fn shadowsocks(outbound: &Value) -> Option<String> {
+    let userinfo = format!(
+        "{}:{}",
+        str(outbound, "/method")?,
+        str(outbound, "/password")?
+    );
+    let mut params = Params::default();
+    let plugin = str(outbound, "/plugin").map(|plugin| match str(outbound, "/plugin_opts") {
+        Some(options) => format!("{plugin};{options}"),
+        None => plugin.to_string(),
+    });
+    params.add("plugin", plugin);
+    let tag = encode(str(outbound, "/tag").unwrap_or_default());
+    Some(format!(
+        "ss://{}@{}/{}#{tag}",
+        URL_SAFE_NO_PAD.encode(userinfo),
+        authority(outbound)?,
+        params.render()
+    ))
+}
+
+fn hysteria2(outbound: &Value) -> Option<String> {
+    let mut params = Params::default();
+    tls_params(outbound, &mut params);
+    params.add("obfs", str(outbound, "/obfs/type"));
+    params.add("obfs-password", str(outbound, "/obfs/password"));
+    params.add("up", outbound.get("up_mbps").and_then(Value::as_u64));
+    params.add("down", outbound.get("down_mbps").and_then(Value::as_u64));
+    link(
+        "hysteria2",
+        &encode(str(outbound, "/password")?),
+        outbound,
+        &params,
+    )
+}
+
+fn tuic(outbound: &Value) -> Option<String> {
+    let mut params = Params::default();
+    params.add("congestion_control", str(outbound, "/congestion_control"));
+    params.add("udp_relay_mode", str(outbound, "/udp_relay_mode"));
+    tls_params(outbound, &mut params);
+    let userinfo = format!(
+        "{}:{}",
+        encode(str(outbound, "/uuid")?),
+        encode(str(outbound, "/password").unwrap_or_default())
+    );
+    link("tuic", &userinfo, outbound, &params)
+}
+
+/// Share link for a node outbound, `None` for unsupported types or missing fields.
+pub fn share_link(outbound: &Value) -> Option<String> {
+    match outbound.get("type")?.as_str()? {
+        "vmess" => vmess(outbound),
+        "vless" => vless(outbound),
+        "trojan" => trojan(outbound),
+        "shadowsocks" => shadowsocks(outbound),
+        "hysteria2" => hysteria2(outbound),
+        "tuic" => tuic(outbound),
+        _ => None,
+    }
+}
+
+/// Render `text` as a QR code of half-block characters, light on dark terminals.
+pub fn qr_code(text: &str) -> Option<String> {
+    let code = QrCode::new(text).ok()?;
+    Some(
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build(),
+    )
+}
@@ -0,0 +1,48 @@
+use std::{
+    env,
+    fs::{self, File, TryLockError},
+    io::Write,
+};
+
+use super::CONFIG_FILE;
+use crate::{Error, Result};
+
+/// Lock file next to the config, holding the pid of the instance writing it.
+const LOCK_FILE: &str = "config.json.lock";
+
+/// Advisory lock on the output directory, released when dropped or the process exits.
+#[derive(Debug)]
+pub struct OutputLock {
+    _file: File,
+}
+
+/// Lock the output directory so daemon, cron and manual runs don't write the
+/// config at the same time.
+pub fn lock_output() -> Result<OutputLock> {
+    let mut file = File::options()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(LOCK_FILE)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let pid = fs::read_to_string(LOCK_FILE).unwrap_or_default();
+            let by = match pid.trim() {
+                "" => String::new(),
+                pid => format!(" (pid {pid})"),
+            };
+            return Err(Error::Io(format!(
+                "Another instance{by} is writing {CONFIG_FILE} in {}, try again once it finishes.",
+                env::current_dir()?.display()
+            )));
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(Error::Io(format!("Can't lock {LOCK_FILE}: {e}")))
+        }
+    }
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    Ok(OutputLock { _file: file })
+}
@@ -0,0 +1,237 @@
+use std::{
+    fs,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{Config, Result};
+
+mod clash;
+mod clipboard;
+mod diff;
+mod git;
+mod history;
+mod links;
+mod lock;
+mod surfboard;
+pub mod surge;
+mod wireguard;
+mod xray;
+
+pub use clash::{clash_config, clash_proxy, CLASH_FILE};
+pub use clipboard::copy_to_clipboard;
+pub use diff::{config_diff, diff_against_existing};
+pub use git::git_commit_config;
+pub use history::{record_history, restore_history};
+pub use links::{qr_code, share_link};
+pub use lock::{lock_output, OutputLock};
+pub use surfboard::{surfboard_config, SURFBOARD_FILE};
+pub use surge::SURGE_FILE;
+pub use wireguard::{export_wireguard, wg_quick_config};
+pub use xray::{xray_config, xray_outbound, XRAY_FILE};
+
+/// Non-empty string at `pointer`.
+fn str<'a>(value: &'a Value, pointer: &str) -> Option<&'a str> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .filter(|v| !v.is_empty())
+}
+
+/// Whether `pointer` is `true`.
+fn enabled(value: &Value, pointer: &str) -> bool {
+    value.pointer(pointer) == Some(&Value::Bool(true))
+}
+
+pub const CONFIG_FILE: &str = "config.json";
+pub const HISTORY_DIR: &str = "history";
+/// Local customizations next to the config, merged into every generated one.
+pub const OVERRIDE_FILE: &str = "override.json";
+
+/// Client the subscription is converted for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+    SingBox,
+    Clash,
+    Surge,
+    Surfboard,
+    Xray,
+}
+
+impl Target {
+    /// `Content-Type` of the rendered config.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Target::SingBox | Target::Xray => "application/json",
+            Target::Clash => "application/yaml",
+            Target::Surge | Target::Surfboard => "text/plain; charset=utf-8",
+        }
+    }
+
+    /// User-Agent sent by default, providers pick the returned format by it.
+    pub fn default_user_agent(&self) -> &'static str {
+        match self {
+            Target::SingBox => "sing-box/1.6.0",
+            // Surge's own format can't be parsed back, Clash YAML can.
+            Target::Clash | Target::Surge | Target::Surfboard => "clash.meta",
+            // v2rayN style base64 share links.
+            Target::Xray => "v2rayN/6.0",
+        }
+    }
+}
+
+/// Write `content` to `path` through a temp file, so readers never see half a file.
+fn write_atomic(path: &str, content: &[u8]) -> Result<()> {
+    let temp_path = format!("{path}.tmp");
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Write the configs of `targets` derived from the sing-box config, returning their paths.
+///
+/// The sing-box config itself is always written as `config.json`, `external` is the
+/// Surge line running it.
+pub fn save_client_configs(
+    data: &Config,
+    targets: &[Target],
+    external: &str,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for target in targets {
+        let path = match target {
+            Target::SingBox => continue,
+            Target::Clash => CLASH_FILE,
+            Target::Surge => SURGE_FILE,
+            Target::Surfboard => SURFBOARD_FILE,
+            Target::Xray => XRAY_FILE,
+        };
+        // config.json is already written, one client failing shouldn't fail the run.
+        let content = match render_client_config(data, *target, Some(external)) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("✖ {e}");
+                continue;
+            }
+        };
+        write_atomic(path, content.as_bytes())?;
+        info!("✅ Wrote {target:?} config to: {path}");
+        paths.push(std::path::absolute(path)?);
+    }
+    Ok(paths)
+}
+
+/// Write `content` to a temp file and rename it over the config, keeping a `.bak` copy.
+/// `verify` runs on the temp file first, when it fails the config is left untouched.
+pub fn replace_config(content: &[u8], verify: impl FnOnce(&str) -> Result<()>) -> Result<()> {
+    let temp_path = format!("{CONFIG_FILE}.tmp");
+    let mut file = File::create(&temp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    if let Err(e) = verify(&temp_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if Path::new(CONFIG_FILE).exists() {
+        fs::copy(CONFIG_FILE, format!("{CONFIG_FILE}.bak"))?;
+        info!("✅ Backup previous config to: {CONFIG_FILE}.bak");
+    }
+    fs::rename(&temp_path, CONFIG_FILE)?;
+    Ok(())
+}
+
+/// The config for `target` derived from the sing-box config, see [`surge::surge_config`]
+/// for `external`.
+pub fn render_client_config(
+    data: &Config,
+    target: Target,
+    external: Option<&str>,
+) -> Result<String> {
+    match target {
+        Target::SingBox => render_config(data),
+        Target::Clash => clash_config(data),
+        Target::Surge => Ok(surge::surge_config(data, external)),
+        Target::Surfboard => surfboard_config(data),
+        Target::Xray => xray_config(data),
+    }
+}
+
+/// Serialize a config the way it is written, with sorted keys.
+pub fn render_config(data: &Config) -> Result<String> {
+    // Sorted keys keep the output stable across runs.
+    let sorted: std::collections::BTreeMap<_, _> = data.iter().collect();
+    Ok(serde_json::to_string_pretty(&sorted)?)
+}
+
+/// Whether the existing config already has exactly this content.
+pub fn config_unchanged(data: &Config) -> Result<bool> {
+    match fs::read_to_string(CONFIG_FILE) {
+        Ok(existing) => Ok(existing == render_config(data)?),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Canonical absolute path of the config, what sing-box is started with.
+///
+/// Symlinks are resolved (e.g. `/tmp` to `/private/tmp` on macOS) so the path stays
+/// valid whatever directory the client later runs sing-box from.
+pub fn config_path() -> Result<PathBuf> {
+    match canonicalize(Path::new(CONFIG_FILE)) {
+        Ok(path) => Ok(path),
+        // Not written yet, the directory itself exists.
+        Err(_) => Ok(canonicalize(Path::new("."))?.join(CONFIG_FILE)),
+    }
+}
+
+/// `fs::canonicalize` without the `\\?\` prefix Windows adds, which most programs
+/// reading the path back don't understand.
+fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    let path = fs::canonicalize(path)?;
+    match path.to_str().and_then(|path| path.strip_prefix(r"\\?\")) {
+        Some(stripped) if cfg!(windows) && !stripped.starts_with("UNC") => Ok(stripped.into()),
+        _ => Ok(path),
+    }
+}
+
+/// The config currently written, if any.
+pub fn existing_config() -> Option<Config> {
+    serde_json::from_slice(&fs::read(CONFIG_FILE).ok()?).ok()
+}
+
+/// Save `data` as the config, see [`replace_config`] for `verify`.
+pub fn save_config(data: &Config, verify: impl FnOnce(&str) -> Result<()>) -> Result<()> {
+    let output_config = render_config(data)?;
+    replace_config(output_config.as_bytes(), verify)?;
+    info!("✅ Conver successfully, save to: {CONFIG_FILE}");
+    Ok(())
+}
+
+/// Format unix seconds as a UTC date time, e.g. `2024-11-08 12:30:00 UTC`.
+pub fn format_timestamp(secs: u64) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let rem = secs % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,62 @@
+use serde_json::Value;
+
+use super::surge::proxy_line;
+use crate::{convert::Node, Config, Error, Result};
+
+pub const SURFBOARD_FILE: &str = "surfboard.conf";
+
+/// Protocols Surfboard speaks, it has no hysteria2, tuic or external proxies.
+const PROTOCOLS: [&str; 5] = ["shadowsocks", "vmess", "trojan", "socks", "http"];
+
+/// Surfboard takes `ws-headers=Host:example.com`, Surge quotes the value.
+fn unquote_headers(line: &str) -> String {
+    let Some((head, rest)) = line.split_once("ws-headers=Host:\"") else {
+        return line.to_string();
+    };
+    match rest.split_once('"') {
+        Some((host, tail)) => format!("{head}ws-headers=Host:{host}{tail}"),
+        None => line.to_string(),
+    }
+}
+
+/// A Surfboard config with every node it supports.
+///
+/// The syntax is Surge's, but Android can't run sing-box as an external proxy, so
+/// only native nodes are listed and a `[General]` section with DNS is required.
+pub fn surfboard_config(data: &Config) -> Result<String> {
+    let lines: Vec<String> = data
+        .get("outbounds")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+        .filter(|node| PROTOCOLS.contains(&node.protocol.as_str()))
+        .filter_map(|node| proxy_line(&node.outbound))
+        .map(|line| unquote_headers(&line))
+        .collect();
+    if lines.is_empty() {
+        return Err(Error::Validation(String::from(
+            "No nodes Surfboard supports, skip writing its config.",
+        )));
+    }
+    let mut members: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.split_once(" = ").map(|(name, _)| name))
+        .collect();
+    members.push("DIRECT");
+
+    let mut output = String::from("[General]\n");
+    output.push_str("dns-server = system, 223.5.5.5, 1.1.1.1\n");
+    output.push_str(
+        "skip-proxy = 127.0.0.1, 192.168.0.0/16, 10.0.0.0/8, 172.16.0.0/12, localhost, *.local\n",
+    );
+    output.push_str("\n[Proxy]\n");
+    for line in &lines {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.push_str("\n[Proxy Group]\n");
+    output.push_str(&format!("Proxy = select, {}\n", members.join(", ")));
+    output.push_str("\n[Rule]\nFINAL,Proxy\n");
+    Ok(output)
+}
@@ -0,0 +1,200 @@
+use serde_json::Value;
+
+use super::{config_path, enabled, str};
+use crate::{
+    convert::{ExternalController, Node},
+    Config, Result,
+};
+
+/// `value` in double quotes, with backslashes and quotes escaped so paths with spaces,
+/// commas or Windows separators survive Surge's parser.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Build the Surge `[Proxy]` line running `exec` as an external proxy.
+pub fn external_line(controller: &ExternalController, exec: &str) -> Result<String> {
+    let mut output = "External = external, ".to_string();
+    output.push_str(&format!("exec = {}, ", quoted(exec)));
+    output.push_str(&format!("local-port = {}, ", controller.port));
+    output.push_str("args = \"run\", ");
+    output.push_str("args = \"-c\", ");
+    let config = config_path()?;
+    output.push_str(&format!(
+        "args = {}, ",
+        quoted(&config.display().to_string())
+    ));
+    output.push_str(&format!("address = {}", controller.address));
+    Ok(output)
+}
+
+pub const SURGE_FILE: &str = "surge.conf";
+
+/// Surge splits on `,` and `=`, keep them out of proxy names.
+fn proxy_name(tag: &str) -> String {
+    tag.replace([',', '='], " ").trim().to_string()
+}
+
+fn tls_params(outbound: &Value, params: &mut Vec<String>) {
+    if let Some(sni) = str(outbound, "/tls/server_name") {
+        params.push(format!("sni={sni}"));
+    }
+    if enabled(outbound, "/tls/insecure") {
+        params.push(String::from("skip-cert-verify=true"));
+    }
+}
+
+/// `ws=true` and friends, `None` when the transport has no Surge equivalent.
+fn transport_params(outbound: &Value, params: &mut Vec<String>) -> Option<()> {
+    match str(outbound, "/transport/type") {
+        None => {}
+        Some("ws") => {
+            params.push(String::from("ws=true"));
+            if let Some(path) = str(outbound, "/transport/path") {
+                params.push(format!("ws-path={path}"));
+            }
+            if let Some(host) = str(outbound, "/transport/headers/Host") {
+                params.push(format!("ws-headers=Host:\"{host}\""));
+            }
+        }
+        Some(_) => return None,
+    }
+    Some(())
+}
+
+/// Native Surge `[Proxy]` line for a node, `None` for what only sing-box speaks.
+pub fn proxy_line(outbound: &Value) -> Option<String> {
+    let name = proxy_name(str(outbound, "/tag")?);
+    let server = str(outbound, "/server")?;
+    let port = outbound.get("server_port")?.as_u64()?;
+    let tls = enabled(outbound, "/tls/enabled");
+    let mut params = Vec::new();
+    let kind = match str(outbound, "/type")? {
+        "shadowsocks" => {
+            params.push(format!("encrypt-method={}", str(outbound, "/method")?));
+            params.push(format!("password={}", str(outbound, "/password")?));
+            match str(outbound, "/plugin") {
+                None => {}
+                Some("obfs-local") => {
+                    for option in str(outbound, "/plugin_opts").unwrap_or_default().split(';') {
+                        match option.split_once('=') {
+                            Some(("obfs", mode)) => params.push(format!("obfs={mode}")),
+                            Some(("obfs-host", host)) => params.push(format!("obfs-host={host}")),
+                            _ => {}
+                        }
+                    }
+                }
+                Some(_) => return None,
+            }
+            "ss"
+        }
+        "vmess" => {
+            params.push(format!("username={}", str(outbound, "/uuid")?));
+            if outbound
+                .get("alter_id")
+                .and_then(Value::as_u64)
+                .unwrap_or(0)
+                == 0
+            {
+                params.push(String::from("vmess-aead=true"));
+            }
+            if tls {
+                params.push(String::from("tls=true"));
+                tls_params(outbound, &mut params);
+            }
+            transport_params(outbound, &mut params)?;
+            "vmess"
+        }
+        "trojan" => {
+            params.push(format!("password={}", str(outbound, "/password")?));
+            tls_params(outbound, &mut params);
+            transport_params(outbound, &mut params)?;
+            "trojan"
+        }
+        "hysteria2" => {
+            // Surge has no salamander obfuscation.
+            if outbound.get("obfs").is_some() {
+                return None;
+            }
+            params.push(format!("password={}", str(outbound, "/password")?));
+            if let Some(down) = outbound.get("down_mbps").and_then(Value::as_u64) {
+                params.push(format!("download-bandwidth={down}"));
+            }
+            tls_params(outbound, &mut params);
+            "hysteria2"
+        }
+        "tuic" => {
+            params.push(format!("uuid={}", str(outbound, "/uuid")?));
+            params.push(format!(
+                "password={}",
+                str(outbound, "/password").unwrap_or_default()
+            ));
+            let alpn = outbound
+                .pointer("/tls/alpn/0")
+                .and_then(Value::as_str)
+                .unwrap_or("h3");
+            params.push(format!("alpn={alpn}"));
+            tls_params(outbound, &mut params);
+            "tuic-v5"
+        }
+        "socks" | "http" => {
+            if let Some(username) = str(outbound, "/username") {
+                params.push(format!("username={username}"));
+                params.push(format!(
+                    "password={}",
+                    str(outbound, "/password").unwrap_or_default()
+                ));
+            }
+            match (str(outbound, "/type")?, tls) {
+                ("socks", false) => "socks5",
+                ("socks", true) => "socks5-tls",
+                (_, false) => "http",
+                (_, true) => "https",
+            }
+        }
+        _ => return None,
+    };
+    let mut line = format!("{name} = {kind}, {server}, {port}");
+    for param in params {
+        line.push_str(", ");
+        line.push_str(&param);
+    }
+    Some(line)
+}
+
+/// A Surge config with every node Surge speaks natively, led by the sing-box `external`
+/// proxy when it runs on this machine.
+pub fn surge_config(data: &Config, external: Option<&str>) -> String {
+    let lines: Vec<String> = data
+        .get("outbounds")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+        .filter_map(|node| proxy_line(&node.outbound))
+        .collect();
+    let mut members: Vec<String> = external
+        .map(|_| String::from("External"))
+        .into_iter()
+        .collect();
+    members.extend(
+        lines
+            .iter()
+            .filter_map(|line| line.split_once(" = ").map(|(name, _)| name.to_string())),
+    );
+    members.push(String::from("DIRECT"));
+
+    let mut output = String::from("[Proxy]\n");
+    if let Some(external) = external {
+        output.push_str(external);
+        output.push('\n');
+    }
+    for line in &lines {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.push_str("\n[Proxy Group]\n");
+    output.push_str(&format!("Proxy = select, {}\n", members.join(", ")));
+    output.push_str("\n[Rule]\nFINAL,Proxy\n");
+    output
+}
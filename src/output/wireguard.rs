@@ -0,0 +1,127 @@
+use std::{fs, path::Path, path::PathBuf};
+
+use serde_json::Value;
+
+use crate::{convert::socket_address, Config, Error, Result};
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(value)) => vec![value.clone()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn field<'a>(value: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| value.get(*name))
+}
+
+/// Render a wg-quick config for a wireguard outbound or endpoint.
+///
+/// Handles the legacy single-peer outbound fields as well as the `peers` list.
+pub fn wg_quick_config(wireguard: &Value) -> Result<String> {
+    let tag = wireguard.get("tag").and_then(Value::as_str).unwrap_or("?");
+    let private_key = wireguard
+        .get("private_key")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::Validation(format!("WireGuard {tag} has no private_key")))?;
+
+    let mut output = String::from("[Interface]\n");
+    output.push_str(&format!("PrivateKey = {private_key}\n"));
+    let addresses = strings(field(wireguard, &["address", "local_address"]));
+    if !addresses.is_empty() {
+        output.push_str(&format!("Address = {}\n", addresses.join(", ")));
+    }
+    if let Some(mtu) = wireguard.get("mtu") {
+        output.push_str(&format!("MTU = {mtu}\n"));
+    }
+
+    let peers = match wireguard.get("peers").and_then(Value::as_array) {
+        Some(peers) => peers.iter().collect(),
+        None => vec![wireguard],
+    };
+    for peer in peers {
+        let public_key = field(peer, &["public_key", "peer_public_key"])
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::Validation(format!("WireGuard {tag} has a peer without key")))?;
+        output.push_str("\n[Peer]\n");
+        output.push_str(&format!("PublicKey = {public_key}\n"));
+        if let Some(key) = peer.get("pre_shared_key").and_then(Value::as_str) {
+            output.push_str(&format!("PresharedKey = {key}\n"));
+        }
+        let allowed_ips = match strings(peer.get("allowed_ips")) {
+            ips if ips.is_empty() => vec![String::from("0.0.0.0/0"), String::from("::/0")],
+            ips => ips,
+        };
+        output.push_str(&format!("AllowedIPs = {}\n", allowed_ips.join(", ")));
+        let server = field(peer, &["address", "server"]).and_then(Value::as_str);
+        let port = field(peer, &["port", "server_port"]);
+        if let (Some(server), Some(port)) = (server, port) {
+            output.push_str(&format!("Endpoint = {}\n", socket_address(server, port)));
+        }
+        if let Some(keepalive) = peer.get("persistent_keepalive_interval") {
+            output.push_str(&format!("PersistentKeepalive = {keepalive}\n"));
+        }
+    }
+    Ok(output)
+}
+
+/// Interface name wg-quick accepts: at most 15 of `[a-zA-Z0-9_=+.-]`.
+fn interface_name(tag: &str, taken: &[String]) -> String {
+    let name: String = tag
+        .chars()
+        .filter(|c| c.is_ascii())
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_=+.-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(15)
+        .collect();
+    let name = name.trim_matches('_').to_string();
+    if !name.is_empty() && !taken.contains(&name) {
+        return name;
+    }
+    (0..)
+        .map(|index| format!("wg{index}"))
+        .find(|name| !taken.contains(name))
+        .unwrap()
+}
+
+/// Write one wg-quick `.conf` per wireguard outbound or endpoint into `dir`.
+pub fn export_wireguard(data: &Config, dir: &Path) -> Result<Vec<PathBuf>> {
+    let wireguards: Vec<&Value> = ["outbounds", "endpoints"]
+        .iter()
+        .filter_map(|section| data.get(*section).and_then(Value::as_array))
+        .flatten()
+        .filter(|value| value.get("type").and_then(Value::as_str) == Some("wireguard"))
+        .collect();
+    if wireguards.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    fs::create_dir_all(dir)?;
+    let mut names = Vec::new();
+    let mut paths = Vec::new();
+    for wireguard in wireguards {
+        let tag = wireguard.get("tag").and_then(Value::as_str).unwrap_or("");
+        let name = interface_name(tag, &names);
+        let path = dir.join(format!("{name}.conf"));
+        fs::write(&path, wg_quick_config(wireguard)?)?;
+        // wg-quick complains about world-readable private keys.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+        names.push(name);
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,193 @@
+use serde_json::{json, Map, Value};
+
+use super::{enabled, str};
+use crate::{
+    convert::{inbound_port, Node},
+    Config, Error, Result,
+};
+
+pub const XRAY_FILE: &str = "xray.json";
+
+/// Xray `streamSettings` from the sing-box TLS and transport blocks.
+fn stream_settings(outbound: &Value) -> Option<Value> {
+    let mut stream = Map::new();
+    let network = match str(outbound, "/transport/type") {
+        None => "tcp",
+        Some("ws") => {
+            let mut ws = Map::new();
+            if let Some(path) = str(outbound, "/transport/path") {
+                ws.insert(String::from("path"), json!(path));
+            }
+            if let Some(host) = str(outbound, "/transport/headers/Host") {
+                ws.insert(String::from("headers"), json!({ "Host": host }));
+            }
+            stream.insert(String::from("wsSettings"), Value::Object(ws));
+            "ws"
+        }
+        Some("grpc") => {
+            stream.insert(
+                String::from("grpcSettings"),
+                json!({ "serviceName": str(outbound, "/transport/service_name").unwrap_or_default() }),
+            );
+            "grpc"
+        }
+        Some("http") => {
+            let mut http = Map::new();
+            if let Some(path) = str(outbound, "/transport/path") {
+                http.insert(String::from("path"), json!(path));
+            }
+            if let Some(host) = outbound.pointer("/transport/host") {
+                http.insert(String::from("host"), host.clone());
+            }
+            stream.insert(String::from("httpSettings"), Value::Object(http));
+            "http"
+        }
+        Some("httpupgrade") => {
+            stream.insert(
+                String::from("httpupgradeSettings"),
+                json!({
+                    "path": str(outbound, "/transport/path").unwrap_or("/"),
+                    "host": str(outbound, "/transport/host").unwrap_or_default(),
+                }),
+            );
+            "httpupgrade"
+        }
+        Some(_) => return None,
+    };
+    stream.insert(String::from("network"), json!(network));
+
+    if enabled(outbound, "/tls/reality/enabled") {
+        stream.insert(String::from("security"), json!("reality"));
+        stream.insert(
+            String::from("realitySettings"),
+            json!({
+                "serverName": str(outbound, "/tls/server_name").unwrap_or_default(),
+                "publicKey": str(outbound, "/tls/reality/public_key").unwrap_or_default(),
+                "shortId": str(outbound, "/tls/reality/short_id").unwrap_or_default(),
+                "fingerprint": str(outbound, "/tls/utls/fingerprint").unwrap_or("chrome"),
+            }),
+        );
+    } else if enabled(outbound, "/tls/enabled") {
+        let mut tls = Map::new();
+        if let Some(sni) = str(outbound, "/tls/server_name") {
+            tls.insert(String::from("serverName"), json!(sni));
+        }
+        if enabled(outbound, "/tls/insecure") {
+            tls.insert(String::from("allowInsecure"), json!(true));
+        }
+        if let Some(alpn) = outbound.pointer("/tls/alpn") {
+            tls.insert(String::from("alpn"), alpn.clone());
+        }
+        if let Some(fingerprint) = str(outbound, "/tls/utls/fingerprint") {
+            tls.insert(String::from("fingerprint"), json!(fingerprint));
+        }
+        stream.insert(String::from("security"), json!("tls"));
+        stream.insert(String::from("tlsSettings"), Value::Object(tls));
+    }
+    Some(Value::Object(stream))
+}
+
+/// Xray outbound for a node, `None` for protocols xray-core doesn't implement.
+pub fn xray_outbound(outbound: &Value) -> Option<Value> {
+    let address = str(outbound, "/server")?;
+    let port = outbound.get("server_port")?.as_u64()?;
+    let (protocol, settings) = match str(outbound, "/type")? {
+        "vmess" => (
+            "vmess",
+            json!({ "vnext": [{ "address": address, "port": port, "users": [{
+                "id": str(outbound, "/uuid")?,
+                "alterId": outbound.get("alter_id").and_then(Value::as_u64).unwrap_or(0),
+                "security": str(outbound, "/security").unwrap_or("auto"),
+            }] }] }),
+        ),
+        "vless" => (
+            "vless",
+            json!({ "vnext": [{ "address": address, "port": port, "users": [{
+                "id": str(outbound, "/uuid")?,
+                "encryption": "none",
+                "flow": str(outbound, "/flow").unwrap_or_default(),
+            }] }] }),
+        ),
+        "trojan" => (
+            "trojan",
+            json!({ "servers": [{
+                "address": address,
+                "port": port,
+                "password": str(outbound, "/password")?,
+            }] }),
+        ),
+        "shadowsocks" => {
+            // SIP003 plugins run outside xray.
+            if outbound.get("plugin").is_some() {
+                return None;
+            }
+            (
+                "shadowsocks",
+                json!({ "servers": [{
+                    "address": address,
+                    "port": port,
+                    "method": str(outbound, "/method")?,
+                    "password": str(outbound, "/password")?,
+                }] }),
+            )
+        }
+        "socks" | "http" => {
+            let mut server = json!({ "address": address, "port": port });
+            if let Some(username) = str(outbound, "/username") {
+                server["users"] = json!([{
+                    "user": username,
+                    "pass": str(outbound, "/password").unwrap_or_default(),
+                }]);
+            }
+            (str(outbound, "/type")?, json!({ "servers": [server] }))
+        }
+        _ => return None,
+    };
+    let mut xray = json!({
+        "tag": str(outbound, "/tag")?,
+        "protocol": protocol,
+        "settings": settings,
+    });
+    xray["streamSettings"] = stream_settings(outbound)?;
+    Some(xray)
+}
+
+/// An Xray-core config with the nodes xray supports, behind a local socks/http inbound.
+///
+/// Traffic goes through the first node, private addresses go direct. Switching nodes
+/// means pointing the routing rule at another tag.
+pub fn xray_config(data: &Config) -> Result<String> {
+    let mut outbounds: Vec<Value> = data
+        .get("outbounds")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+        .filter_map(|node| xray_outbound(&node.outbound))
+        .collect();
+    let Some(first) = outbounds.first().and_then(|o| o.get("tag")).cloned() else {
+        return Err(Error::Validation(String::from(
+            "No nodes Xray supports, skip writing its config.",
+        )));
+    };
+    outbounds.push(json!({ "tag": "direct", "protocol": "freedom" }));
+    outbounds.push(json!({ "tag": "block", "protocol": "blackhole" }));
+
+    let (listen, port) = inbound_port(data).unwrap_or((String::from("127.0.0.1"), 7890));
+    let config = json!({
+        "log": { "loglevel": "warning" },
+        "inbounds": [
+            { "tag": "socks-in", "listen": listen, "port": port, "protocol": "socks", "settings": { "udp": true } },
+            { "tag": "http-in", "listen": listen, "port": port.saturating_add(1), "protocol": "http" },
+        ],
+        "outbounds": outbounds,
+        "routing": {
+            "domainStrategy": "IPIfNonMatch",
+            "rules": [
+                { "type": "field", "ip": ["geoip:private"], "outboundTag": "direct" },
+                { "type": "field", "network": "tcp,udp", "outboundTag": first },
+            ],
+        },
+    });
+    Ok(serde_json::to_string_pretty(&config)?)
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, io::IsTerminal};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::{output::CONFIG_FILE, Error, Node, Result};
+
+/// What the user chose in the picker.
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Unselected node tags.
+    pub removed: Vec<String>,
+    /// New tags by original tag.
+    pub renames: HashMap<String, String>,
+    pub groups: bool,
+    pub region_groups: bool,
+}
+
+struct Item {
+    tag: String,
+    name: String,
+    protocol: String,
+    selected: bool,
+}
+
+struct Picker {
+    items: Vec<Item>,
+    state: ListState,
+    /// Tag being typed while renaming the highlighted node.
+    editing: Option<String>,
+    groups: bool,
+    region_groups: bool,
+}
+
+impl Picker {
+    fn current(&mut self) -> Option<&mut Item> {
+        let index = self.state.selected()?;
+        self.items.get_mut(index)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(2)]).areas(frame.area());
+
+        let selected = self.items.iter().filter(|item| item.selected).count();
+        let items: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| {
+                let mark = if item.selected { "[x]" } else { "[ ]" };
+                let renamed = if item.name != item.tag {
+                    format!("  (was {})", item.tag)
+                } else {
+                    String::new()
+                };
+                ListItem::new(format!("{mark} {} · {}{renamed}", item.name, item.protocol))
+            })
+            .collect();
+        let title = format!(" Nodes {selected}/{} ", self.items.len());
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let status = match &self.editing {
+            Some(name) => vec![
+                Line::from(format!("Rename: {name}_")),
+                Line::from("enter save · esc cancel"),
+            ],
+            None => vec![
+                Line::from(format!(
+                    "groups: {} · region groups: {}",
+                    if self.groups { "on" } else { "off" },
+                    if self.region_groups { "on" } else { "off" },
+                )),
+                Line::from(
+                    "space toggle · a all · e rename · g groups · r region groups · enter write · q abort",
+                ),
+            ],
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    /// Handle one key, returning `Some(true)` to write and `Some(false)` to abort.
+    fn key(&mut self, code: KeyCode) -> Option<bool> {
+        if let Some(name) = &mut self.editing {
+            match code {
+                KeyCode::Char(c) => name.push(c),
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Enter => {
+                    let name = self.editing.take().unwrap_or_default();
+                    if let Some(item) = self.current().filter(|_| !name.trim().is_empty()) {
+                        item.name = name.trim().to_string();
+                    }
+                }
+                KeyCode::Esc => self.editing = None,
+                _ => {}
+            }
+            return None;
+        }
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+            KeyCode::Char(' ') => {
+                if let Some(item) = self.current() {
+                    item.selected = !item.selected;
+                }
+            }
+            KeyCode::Char('a') => {
+                let all = self.items.iter().all(|item| item.selected);
+                self.items.iter_mut().for_each(|item| item.selected = !all);
+            }
+            KeyCode::Char('e') => self.editing = self.current().map(|item| item.name.clone()),
+            KeyCode::Char('g') => self.groups = !self.groups,
+            KeyCode::Char('r') => self.region_groups = !self.region_groups,
+            KeyCode::Enter => return Some(true),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(false),
+            _ => {}
+        }
+        None
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<bool> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(write) = self.key(key.code) {
+                    return Ok(write);
+                }
+            }
+        }
+    }
+}
+
+/// Let the user pick, rename and group nodes in a terminal UI.
+///
+/// Fails with [`Error::Aborted`] when the user quits without writing.
+pub fn pick_nodes(nodes: &[Node], groups: bool, region_groups: bool) -> Result<Selection> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(Error::Validation(String::from(
+            "The node picker needs an interactive terminal.",
+        )));
+    }
+    let mut picker = Picker {
+        items: nodes
+            .iter()
+            .map(|node| Item {
+                tag: node.tag.clone(),
+                name: node.tag.clone(),
+                protocol: node.protocol.clone(),
+                selected: true,
+            })
+            .collect(),
+        state: ListState::default().with_selected(Some(0)),
+        editing: None,
+        groups,
+        region_groups,
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let write = picker.run(&mut terminal);
+    ratatui::restore();
+    if !write? {
+        return Err(Error::Aborted(CONFIG_FILE.to_string()));
+    }
+
+    let mut selection = Selection {
+        groups: picker.groups,
+        region_groups: picker.region_groups,
+        ..Selection::default()
+    };
+    for item in picker.items {
+        if !item.selected {
+            selection.removed.push(item.tag);
+        } else if item.name != item.tag {
+            selection.renames.insert(item.tag, item.name);
+        }
+    }
+    Ok(selection)
+}
@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{keychain, settings::default_settings_path, Error, Result, Target};
+
+/// A named subscription saved with `profile add`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    /// Empty when the urls are kept in the Keychain.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub url: Vec<String>,
+    pub client: Target,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_dir: Option<PathBuf>,
+    /// The urls are stored in the macOS Keychain under the profile name.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keychain: bool,
+}
+
+impl Profile {
+    /// Subscription urls of profile `name`, read from the Keychain if stored there.
+    pub fn urls(&self, name: &str) -> Result<Vec<String>> {
+        if self.keychain {
+            keychain::load_urls(name)
+        } else {
+            Ok(self.url.clone())
+        }
+    }
+}
+
+/// Saved profiles and the one `update` uses without a name.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profiles {
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// `profiles.toml` next to the settings file.
+pub fn default_profiles_path() -> PathBuf {
+    default_settings_path().with_file_name("profiles.toml")
+}
+
+impl Profiles {
+    /// Read profiles from `path`, a missing file has none.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Profiles::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::Io(format!("Can't read profiles {}: {e}", path.display())))?;
+        toml::from_str(&content)
+            .map_err(|e| Error::Parse(format!("Can't parse profiles {}: {e}", path.display())))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| Error::Parse(format!("Can't serialize profiles: {e}")))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// The profile called `name`, or the default one when `name` is `None`.
+    pub fn get(&self, name: Option<&str>) -> Result<(&str, &Profile)> {
+        let Some(name) = name.or(self.default.as_deref()) else {
+            return Err(Error::Validation(String::from(
+                "No profile given and no default set, see `profile use`.",
+            )));
+        };
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, profile)| (name.as_str(), profile))
+            .ok_or_else(|| Error::Validation(format!("No profile named {name}.")))
+    }
+
+    /// Remove a profile, clearing the default if it pointed at it.
+    pub fn remove(&mut self, name: &str) -> Result<Profile> {
+        let profile = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| Error::Validation(format!("No profile named {name}.")))?;
+        if profile.keychain {
+            keychain::delete_urls(name)?;
+        }
+        if self.default.as_deref() == Some(name) {
+            self.default = None;
+        }
+        Ok(profile)
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    convert::validate_config,
+    metrics::{metrics_handler, Metrics},
+    notify::{notify, Event, NotifyOptions},
+    output::render_client_config,
+    source::{check_url, redact_url},
+    Converter, Error, Result, Subscription, Target,
+};
+
+/// What every request is converted with.
+pub struct ServeState {
+    pub converter: Converter,
+    /// Hosts accepted in `url` besides public https ones, see [`check_url`].
+    pub allowed_hosts: Vec<String>,
+    /// Told about failed conversions and node changes of a subscription.
+    pub notify: NotifyOptions,
+    /// Node tags last served per subscription url.
+    pub tags: Mutex<HashMap<String, Vec<String>>>,
+    /// Conversion counters exposed on `/metrics`.
+    pub metrics: Arc<Metrics>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubQuery {
+    url: String,
+    #[serde(default = "default_target")]
+    target: String,
+}
+
+fn default_target() -> String {
+    String::from("sing-box")
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Error::Url(_) => StatusCode::BAD_REQUEST,
+            Error::Network(_) => StatusCode::BAD_GATEWAY,
+            Error::Parse(_) | Error::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, format!("{self}\n")).into_response()
+    }
+}
+
+async fn convert(state: &ServeState, query: &SubQuery) -> Result<(Target, String)> {
+    let target = Target::from_str(&query.target, true).map_err(|_| {
+        let targets: Vec<String> = Target::value_variants()
+            .iter()
+            .filter_map(|target| Some(target.to_possible_value()?.get_name().to_string()))
+            .collect();
+        Error::Url(format!(
+            "Unsupported target {}, expected one of: {}",
+            query.target,
+            targets.join(", ")
+        ))
+    })?;
+    let url = check_url(&query.url, &state.allowed_hosts)?;
+
+    let mut fetch = state.converter.fetch.clone();
+    fetch.user_agent = target.default_user_agent().to_string();
+    let subscription = Subscription::fetch(&url, &fetch).await?;
+    let tags: Vec<String> = subscription
+        .nodes()
+        .into_iter()
+        .map(|node| node.tag)
+        .collect();
+    let quota = subscription.quota;
+    let conversion = state.converter.convert(subscription).await?;
+    let problems = validate_config(&conversion.config);
+    if !problems.is_empty() {
+        return Err(Error::Validation(format!(
+            "Generated config is invalid:\n    {}",
+            problems.join("\n    ")
+        )));
+    }
+
+    let previous = state.tags.lock().unwrap().insert(url, tags.clone());
+    if let Some(event) = previous.and_then(|old| Event::nodes_changed(&old, &tags)) {
+        notify(&state.notify, &state.converter.fetch, &event).await;
+    }
+    // No sing-box runs next to the HTTP client, Surge gets its nodes without External.
+    let config = render_client_config(&conversion.config, target, None)?;
+    state
+        .metrics
+        .record_success(tags.len(), Option::as_slice(&quota));
+    Ok((target, config))
+}
+
+async fn sub(State(state): State<Arc<ServeState>>, Query(query): Query<SubQuery>) -> Response {
+    match convert(&state, &query).await {
+        Ok((target, config)) => {
+            info!(
+                "✅ Converted {} for {}",
+                redact_url(&query.url),
+                query.target
+            );
+            ([(CONTENT_TYPE, target.content_type())], config).into_response()
+        }
+        Err(e) => {
+            warn!("✖ Converting {} failed: {e}", redact_url(&query.url));
+            state.metrics.record_failure();
+            let event = Event::Failed {
+                error: format!("{}: {e}", redact_url(&query.url)),
+            };
+            notify(&state.notify, &state.converter.fetch, &event).await;
+            e.into_response()
+        }
+    }
+}
+
+async fn metrics(State(state): State<Arc<ServeState>>) -> impl IntoResponse {
+    metrics_handler(State(state.metrics.clone())).await
+}
+
+/// Serve `GET /sub?url=...&target=sing-box|clash|surge|surfboard|xray` and `GET /metrics` until the process is stopped.
+pub async fn serve(address: SocketAddr, state: ServeState) -> Result<()> {
+    let app = Router::new()
+        .route("/sub", get(sub))
+        .route("/metrics", get(metrics))
+        .with_state(Arc::new(state));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("✅ Serving conversions on: http://{address}/sub?url=...");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::{ServiceOptions, SERVICE_LABEL};
+use crate::{Error, Result};
+
+fn home() -> Result<PathBuf> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::Install(String::from("HOME is not set.")))
+}
+
+fn plist_path() -> Result<PathBuf> {
+    Ok(home()?
+        .join("Library/LaunchAgents")
+        .join(format!("{SERVICE_LABEL}.plist")))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn string(value: impl AsRef<str>) -> String {
+    format!("<string>{}</string>", escape(value.as_ref()))
+}
+
+/// The launchd agent plist running the converter at load and every interval.
+pub fn plist(options: &ServiceOptions, log: &Path) -> String {
+    let mut arguments = vec![string(options.program.display().to_string())];
+    arguments.extend(options.args.iter().map(string));
+    let log = string(log.display().to_string());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    {}
+    <key>ProgramArguments</key>
+    <array>
+        {}
+    </array>
+    <key>WorkingDirectory</key>
+    {}
+    <key>StartInterval</key>
+    <integer>{}</integer>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>StandardOutPath</key>
+    {log}
+    <key>StandardErrorPath</key>
+    {log}
+</dict>
+</plist>
+"#,
+        string(SERVICE_LABEL),
+        arguments.join("\n        "),
+        string(options.working_dir.display().to_string()),
+        options.interval.as_secs()
+    )
+}
+
+fn launchctl(args: &[&str]) -> Result<std::process::Output> {
+    Command::new("launchctl")
+        .args(args)
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run launchctl: {e}")))
+}
+
+pub fn install(options: &ServiceOptions) -> Result<PathBuf> {
+    let path = plist_path()?;
+    let log = home()?.join("Library/Logs/external-convertor.log");
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let path_str = path.display().to_string();
+    if path.exists() {
+        // Reinstalling replaces the running agent.
+        launchctl(&["unload", &path_str])?;
+    }
+    fs::write(&path, plist(options, &log))?;
+
+    let load = launchctl(&["load", "-w", &path_str])?;
+    if !load.status.success() {
+        return Err(Error::Install(format!(
+            "launchctl load failed: {}",
+            String::from_utf8_lossy(&load.stderr).trim()
+        )));
+    }
+    Ok(path)
+}
+
+pub fn uninstall() -> Result<PathBuf> {
+    let path = plist_path()?;
+    if !path.exists() {
+        return Err(Error::Install(format!(
+            "No service installed at {}.",
+            path.display()
+        )));
+    }
+    launchctl(&["unload", "-w", &path.display().to_string()])?;
+    fs::remove_file(&path)?;
+    Ok(path)
+}
@@ -0,0 +1,87 @@
+use std::{path::PathBuf, process::Command, time::Duration};
+
+use crate::{Error, Result};
+
+mod launchd;
+mod systemd;
+
+/// Label of the installed service, also used for its file names.
+pub const SERVICE_LABEL: &str = "io.github.external-convertor";
+
+/// What the scheduled service runs.
+#[derive(Debug, Clone)]
+pub struct ServiceOptions {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    pub interval: Duration,
+    /// Install for the whole system instead of the current user (systemd only).
+    pub system: bool,
+    /// Config the updates write, run by the sing-box unit on Linux.
+    pub config_path: PathBuf,
+    /// sing-box binary, no sing-box unit is installed without it.
+    pub sing_box: Option<PathBuf>,
+}
+
+/// Install and load a service running the converter every `interval`, returning its files.
+pub fn install_service(options: &ServiceOptions) -> Result<Vec<PathBuf>> {
+    if cfg!(target_os = "macos") {
+        if options.system {
+            return Err(Error::Install(String::from(
+                "System scope is only supported with systemd.",
+            )));
+        }
+        Ok(vec![launchd::install(options)?])
+    } else if cfg!(target_os = "linux") {
+        systemd::install(options)
+    } else {
+        Err(unsupported())
+    }
+}
+
+/// Stop and delete the installed service, returning the removed files.
+pub fn uninstall_service(system: bool) -> Result<Vec<PathBuf>> {
+    if cfg!(target_os = "macos") {
+        Ok(vec![launchd::uninstall()?])
+    } else if cfg!(target_os = "linux") {
+        systemd::uninstall(system)
+    } else {
+        Err(unsupported())
+    }
+}
+
+/// How a running sing-box was told about the new config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reload {
+    /// The installed systemd unit was reloaded.
+    Service,
+    /// A running sing-box process got SIGHUP.
+    Signal,
+    NotRunning,
+}
+
+/// Make a running sing-box pick up the new config.
+pub fn reload_sing_box() -> Result<Reload> {
+    if cfg!(target_os = "linux") && systemd::reload()? {
+        return Ok(Reload::Service);
+    }
+    let exec = Command::new("pkill")
+        .args(["-HUP", "-x", "sing-box"])
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run pkill: {e}")))?;
+    // pkill exits with 1 when no process matched.
+    match exec.status.code() {
+        Some(0) => Ok(Reload::Signal),
+        Some(1) => Ok(Reload::NotRunning),
+        _ => Err(Error::Install(format!(
+            "Failed to signal sing-box: {}",
+            String::from_utf8_lossy(&exec.stderr).trim()
+        ))),
+    }
+}
+
+fn unsupported() -> Error {
+    Error::Install(String::from(
+        "Services are only supported with launchd on macOS and systemd on Linux.",
+    ))
+}
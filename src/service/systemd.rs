@@ -0,0 +1,191 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+use super::ServiceOptions;
+use crate::{Error, Result};
+
+const UPDATE_UNIT: &str = "external-convertor.service";
+const TIMER_UNIT: &str = "external-convertor.timer";
+const SING_BOX_UNIT: &str = "external-convertor-sing-box.service";
+
+fn unit_dir(system: bool) -> Result<PathBuf> {
+    if system {
+        return Ok(PathBuf::from("/etc/systemd/system"));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| Error::Install(String::from("HOME is not set.")))?;
+    Ok(base.join("systemd/user"))
+}
+
+/// Quote a word for an `ExecStart=` line.
+fn quote(word: &str) -> String {
+    if word.is_empty() || word.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word.to_string()
+    }
+}
+
+fn command_line(program: &str, args: &[String]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .map(quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The oneshot unit running a single update.
+pub fn update_unit(options: &ServiceOptions) -> String {
+    format!(
+        "[Unit]
+Description=Update the sing-box config from the subscription
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=oneshot
+WorkingDirectory={}
+ExecStart={}
+",
+        options.working_dir.display(),
+        command_line(&options.program.display().to_string(), &options.args)
+    )
+}
+
+/// The timer starting the update unit after boot and every interval.
+pub fn timer_unit(options: &ServiceOptions) -> String {
+    format!(
+        "[Unit]
+Description=Update the sing-box config every {secs}s
+
+[Timer]
+OnBootSec=2min
+OnUnitActiveSec={secs}s
+Persistent=true
+
+[Install]
+WantedBy=timers.target
+",
+        secs = options.interval.as_secs()
+    )
+}
+
+/// A long running sing-box using the generated config.
+pub fn sing_box_unit(options: &ServiceOptions, sing_box: &str) -> String {
+    let target = if options.system {
+        "multi-user.target"
+    } else {
+        "default.target"
+    };
+    format!(
+        "[Unit]
+Description=sing-box with the converted subscription config
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+ExecStart={}
+ExecReload=/bin/kill -HUP $MAINPID
+Restart=on-failure
+
+[Install]
+WantedBy={target}
+",
+        command_line(
+            sing_box,
+            &[
+                String::from("run"),
+                String::from("-c"),
+                options.config_path.display().to_string(),
+            ]
+        )
+    )
+}
+
+fn systemctl(system: bool, args: &[&str]) -> Result<()> {
+    let mut command = Command::new("systemctl");
+    if !system {
+        command.arg("--user");
+    }
+    let exec = command
+        .args(args)
+        .output()
+        .map_err(|e| Error::Install(format!("Can't run systemctl: {e}")))?;
+    if !exec.status.success() {
+        return Err(Error::Install(format!(
+            "systemctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&exec.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+pub fn install(options: &ServiceOptions) -> Result<Vec<PathBuf>> {
+    let dir = unit_dir(options.system)?;
+    fs::create_dir_all(&dir)?;
+
+    let mut units = vec![
+        (UPDATE_UNIT, update_unit(options)),
+        (TIMER_UNIT, timer_unit(options)),
+    ];
+    if let Some(sing_box) = &options.sing_box {
+        units.push((
+            SING_BOX_UNIT,
+            sing_box_unit(options, &sing_box.display().to_string()),
+        ));
+    }
+    let mut paths = Vec::new();
+    for (name, content) in &units {
+        let path = dir.join(name);
+        fs::write(&path, content)?;
+        paths.push(path);
+    }
+
+    systemctl(options.system, &["daemon-reload"])?;
+    systemctl(options.system, &["enable", "--now", TIMER_UNIT])?;
+    // Write the config once so sing-box has something to start with.
+    systemctl(options.system, &["start", UPDATE_UNIT])?;
+    if options.sing_box.is_some() {
+        systemctl(options.system, &["enable", "--now", SING_BOX_UNIT])?;
+    }
+    Ok(paths)
+}
+
+pub fn uninstall(system: bool) -> Result<Vec<PathBuf>> {
+    let dir = unit_dir(system)?;
+    let paths: Vec<PathBuf> = [TIMER_UNIT, SING_BOX_UNIT, UPDATE_UNIT]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect();
+    if paths.is_empty() {
+        return Err(Error::Install(format!(
+            "No service installed in {}.",
+            dir.display()
+        )));
+    }
+    for path in &paths {
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            // The update unit is only ever started by the timer.
+            if name != UPDATE_UNIT {
+                systemctl(system, &["disable", "--now", name])?;
+            }
+        }
+        fs::remove_file(path)?;
+    }
+    systemctl(system, &["daemon-reload"])?;
+    Ok(paths)
+}
+
+/// Reload the sing-box unit of whichever scope has it running.
+pub fn reload() -> Result<bool> {
+    for system in [false, true] {
+        if systemctl(system, &["is-active", "--quiet", SING_BOX_UNIT]).is_ok() {
+            systemctl(system, &["reload-or-restart", SING_BOX_UNIT])?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,90 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    convert::{DnsPreset, RoutePreset, Version},
+    Error, Result, Target,
+};
+
+/// Persistent defaults read from `config.toml`, command line flags take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    /// A single url or a list of urls to merge.
+    pub url: Urls,
+    pub client: Option<Target>,
+    pub out_dir: Option<PathBuf>,
+    pub template: Option<PathBuf>,
+    pub patch: Option<PathBuf>,
+    pub dns: Option<DnsPreset>,
+    pub ruleset: Option<RoutePreset>,
+    pub block_ads: bool,
+    pub groups: bool,
+    pub region_groups: bool,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+    pub allow_host: Vec<String>,
+    pub sing_box_version: Option<Version>,
+    pub exec: Option<PathBuf>,
+    pub no_install: bool,
+    pub notify_webhook: Option<String>,
+    pub telegram_token: Option<String>,
+    pub telegram_chat: Option<String>,
+    pub notify_desktop: bool,
+    pub pre_hook: Option<String>,
+    pub post_hook: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(untagged)]
+pub enum Urls {
+    #[default]
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Urls {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            Urls::None => Vec::new(),
+            Urls::One(url) => vec![url],
+            Urls::Many(urls) => urls,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/external-convertor/config.toml`, falling back to `~/.config`.
+pub fn default_settings_path() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from(".config"));
+    base.join("external-convertor").join("config.toml")
+}
+
+/// Read settings from `path`, relative paths inside are resolved against its directory.
+pub fn load_settings(path: &Path) -> Result<Settings> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Io(format!("Can't read settings {}: {e}", path.display())))?;
+    let mut settings: Settings = toml::from_str(&content)
+        .map_err(|e| Error::Parse(format!("Can't parse settings {}: {e}", path.display())))?;
+
+    let base = path.parent().unwrap_or(Path::new("."));
+    for file in [
+        &mut settings.out_dir,
+        &mut settings.template,
+        &mut settings.patch,
+        &mut settings.exec,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *file = base.join(&*file);
+    }
+    Ok(settings)
+}
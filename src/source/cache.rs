@@ -0,0 +1,108 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    encryption::{open, seal, Encryption},
+    Result,
+};
+
+/// A raw subscription response kept between runs.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Raw `subscription-userinfo` header of the cached response.
+    pub userinfo: Option<String>,
+    /// `Content-Type` of the cached response, a hint for parsing the body.
+    pub content_type: Option<String>,
+    /// Unix seconds of the last successful fetch.
+    pub fetched_at: u64,
+}
+
+/// `$XDG_CACHE_HOME/external-convertor`, falling back to `~/.cache/external-convertor`.
+pub fn default_cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|| PathBuf::from(".cache"));
+    base.join("external-convertor")
+}
+
+fn cache_key(url: &str) -> String {
+    Sha256::digest(url.as_bytes())[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub fn load(dir: &Path, url: &str, encryption: Option<&Encryption>) -> Option<CacheEntry> {
+    let key = cache_key(url);
+    let read = |name: String| open(encryption, fs::read(dir.join(name)).ok()?).ok();
+    let meta: Value = serde_json::from_slice(&read(format!("{key}.meta"))?).ok()?;
+    let body = read(format!("{key}.body"))?;
+    let field = |name: &str| meta.get(name).and_then(Value::as_str).map(String::from);
+    Some(CacheEntry {
+        body,
+        etag: field("etag"),
+        last_modified: field("last_modified"),
+        userinfo: field("userinfo"),
+        content_type: field("content_type"),
+        fetched_at: meta.get("fetched_at").and_then(Value::as_u64).unwrap_or(0),
+    })
+}
+
+/// Write `entry`, body and metadata encrypted when a key is given since both hold secrets.
+pub fn store(
+    dir: &Path,
+    url: &str,
+    entry: &CacheEntry,
+    encryption: Option<&Encryption>,
+) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let key = cache_key(url);
+    let meta = serde_json::json!({
+        "url": url,
+        "etag": entry.etag,
+        "last_modified": entry.last_modified,
+        "userinfo": entry.userinfo,
+        "content_type": entry.content_type,
+        "fetched_at": entry.fetched_at,
+    });
+    fs::write(
+        dir.join(format!("{key}.body")),
+        seal(encryption, &entry.body)?,
+    )?;
+    fs::write(
+        dir.join(format!("{key}.meta")),
+        seal(encryption, &serde_json::to_vec_pretty(&meta)?)?,
+    )?;
+    Ok(())
+}
+
+/// Format a duration in seconds as e.g. `2d 3h`, `3h 12m` or `45s`.
+pub fn format_age(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {}s", secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
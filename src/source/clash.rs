@@ -0,0 +1,278 @@
+use serde_json::{json, Map, Value};
+use tracing::{debug, warn};
+
+use super::links::{node_config, parse_mbps, reality, transport, utls};
+use crate::{Config, Error, Result};
+
+/// String field of a Clash proxy, numbers included.
+fn field(proxy: &Value, name: &str) -> Option<String> {
+    match proxy.get(name)? {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn flag(proxy: &Value, name: &str) -> bool {
+    proxy.get(name).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn outbound(proxy: &Value, protocol: &str) -> Result<Map<String, Value>> {
+    let name =
+        field(proxy, "name").ok_or_else(|| Error::Parse(String::from("Proxy without name")))?;
+    let server = field(proxy, "server")
+        .ok_or_else(|| Error::Parse(format!("Proxy {name} without server")))?;
+    let port: u16 = field(proxy, "port")
+        .and_then(|port| port.parse().ok())
+        .ok_or_else(|| Error::Parse(format!("Proxy {name} without port")))?;
+    let mut outbound = Map::new();
+    outbound.insert(String::from("type"), json!(protocol));
+    outbound.insert(String::from("tag"), json!(name));
+    outbound.insert(String::from("server"), json!(server));
+    outbound.insert(String::from("server_port"), json!(port));
+    Ok(outbound)
+}
+
+/// TLS block from `sni`/`servername`, `alpn`, `skip-cert-verify` and `client-fingerprint`.
+fn tls(proxy: &Value) -> Value {
+    let mut tls = Map::new();
+    tls.insert(String::from("enabled"), json!(true));
+    if let Some(sni) = field(proxy, "sni").or_else(|| field(proxy, "servername")) {
+        tls.insert(String::from("server_name"), json!(sni));
+    }
+    if flag(proxy, "skip-cert-verify") {
+        tls.insert(String::from("insecure"), json!(true));
+    }
+    if let Some(alpn) = proxy.get("alpn").and_then(Value::as_array) {
+        tls.insert(String::from("alpn"), Value::Array(alpn.clone()));
+    }
+    if let Some(fingerprint) = field(proxy, "client-fingerprint") {
+        tls.insert(String::from("utls"), utls(&fingerprint));
+    }
+    if let Some(options) = proxy.get("reality-opts") {
+        reality(
+            &mut tls,
+            field(options, "public-key").as_deref(),
+            field(options, "short-id").as_deref(),
+        );
+    }
+    Value::Object(tls)
+}
+
+/// Transport from `network` and its `ws-opts`, `h2-opts`, `http-opts` or `grpc-opts`.
+fn proxy_transport(proxy: &Value) -> Option<Value> {
+    let network = field(proxy, "network").unwrap_or_else(|| String::from("tcp"));
+    let options = proxy
+        .get(format!("{network}-opts").as_str())
+        .cloned()
+        .unwrap_or(Value::Null);
+    let path = match options.get("path") {
+        Some(Value::Array(paths)) => paths.first().and_then(Value::as_str).map(String::from),
+        _ => field(&options, "path"),
+    };
+    let host = match options.get("host") {
+        Some(Value::Array(hosts)) => Some(
+            hosts
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        _ => options
+            .pointer("/headers/Host")
+            .and_then(Value::as_str)
+            .map(String::from),
+    };
+    let service_name = field(&options, "grpc-service-name");
+    transport(
+        &network,
+        host.as_deref(),
+        path.as_deref(),
+        service_name.as_deref(),
+    )
+}
+
+fn shadowsocks(proxy: &Value) -> Result<Value> {
+    let mut outbound = outbound(proxy, "shadowsocks")?;
+    outbound.insert(String::from("method"), json!(field(proxy, "cipher")));
+    outbound.insert(String::from("password"), json!(field(proxy, "password")));
+    let options = proxy.get("plugin-opts").cloned().unwrap_or(Value::Null);
+    match field(proxy, "plugin").as_deref() {
+        Some("obfs") => {
+            let mut plugin_opts = format!(
+                "obfs={}",
+                field(&options, "mode").unwrap_or_else(|| String::from("http"))
+            );
+            if let Some(host) = field(&options, "host") {
+                plugin_opts.push_str(&format!(";obfs-host={host}"));
+            }
+            outbound.insert(String::from("plugin"), json!("obfs-local"));
+            outbound.insert(String::from("plugin_opts"), json!(plugin_opts));
+        }
+        Some("v2ray-plugin") => {
+            let mut plugin_opts = vec![format!(
+                "mode={}",
+                field(&options, "mode").unwrap_or_else(|| String::from("websocket"))
+            )];
+            if flag(&options, "tls") {
+                plugin_opts.push(String::from("tls"));
+            }
+            if let Some(host) = field(&options, "host") {
+                plugin_opts.push(format!("host={host}"));
+            }
+            if let Some(path) = field(&options, "path") {
+                plugin_opts.push(format!("path={path}"));
+            }
+            outbound.insert(String::from("plugin"), json!("v2ray-plugin"));
+            outbound.insert(String::from("plugin_opts"), json!(plugin_opts.join(";")));
+        }
+        Some(plugin) => {
+            return Err(Error::Parse(format!(
+                "Unsupported shadowsocks plugin {plugin}"
+            )))
+        }
+        None => {}
+    }
+    Ok(Value::Object(outbound))
+}
+
+fn vmess(proxy: &Value) -> Result<Value> {
+    let mut outbound = outbound(proxy, "vmess")?;
+    outbound.insert(String::from("uuid"), json!(field(proxy, "uuid")));
+    outbound.insert(
+        String::from("security"),
+        json!(field(proxy, "cipher").unwrap_or_else(|| String::from("auto"))),
+    );
+    let alter_id: u64 = field(proxy, "alterId")
+        .and_then(|aid| aid.parse().ok())
+        .unwrap_or_default();
+    outbound.insert(String::from("alter_id"), json!(alter_id));
+    if flag(proxy, "tls") {
+        outbound.insert(String::from("tls"), tls(proxy));
+    }
+    if let Some(transport) = proxy_transport(proxy) {
+        outbound.insert(String::from("transport"), transport);
+    }
+    Ok(Value::Object(outbound))
+}
+
+fn vless(proxy: &Value) -> Result<Value> {
+    let mut outbound = outbound(proxy, "vless")?;
+    outbound.insert(String::from("uuid"), json!(field(proxy, "uuid")));
+    if let Some(flow) = field(proxy, "flow") {
+        outbound.insert(String::from("flow"), json!(flow));
+    }
+    if flag(proxy, "tls") || proxy.get("reality-opts").is_some() {
+        outbound.insert(String::from("tls"), tls(proxy));
+    }
+    if let Some(transport) = proxy_transport(proxy) {
+        outbound.insert(String::from("transport"), transport);
+    }
+    Ok(Value::Object(outbound))
+}
+
+fn trojan(proxy: &Value) -> Result<Value> {
+    let mut outbound = outbound(proxy, "trojan")?;
+    outbound.insert(String::from("password"), json!(field(proxy, "password")));
+    outbound.insert(String::from("tls"), tls(proxy));
+    if let Some(transport) = proxy_transport(proxy) {
+        outbound.insert(String::from("transport"), transport);
+    }
+    Ok(Value::Object(outbound))
+}
+
+fn hysteria2(proxy: &Value) -> Result<Value> {
+    let mut outbound = outbound(proxy, "hysteria2")?;
+    outbound.insert(String::from("password"), json!(field(proxy, "password")));
+    for (name, field_name) in [("up", "up_mbps"), ("down", "down_mbps")] {
+        if let Some(mbps) = field(proxy, name).as_deref().and_then(parse_mbps) {
+            outbound.insert(String::from(field_name), json!(mbps));
+        }
+    }
+    if let Some(obfs) = field(proxy, "obfs") {
+        outbound.insert(
+            String::from("obfs"),
+            json!({ "type": obfs, "password": field(proxy, "obfs-password").unwrap_or_default() }),
+        );
+    }
+    outbound.insert(String::from("tls"), tls(proxy));
+    Ok(Value::Object(outbound))
+}
+
+fn tuic(proxy: &Value) -> Result<Value> {
+    let mut outbound = outbound(proxy, "tuic")?;
+    outbound.insert(String::from("uuid"), json!(field(proxy, "uuid")));
+    outbound.insert(String::from("password"), json!(field(proxy, "password")));
+    if let Some(congestion) = field(proxy, "congestion-controller") {
+        outbound.insert(String::from("congestion_control"), json!(congestion));
+    }
+    if let Some(mode) = field(proxy, "udp-relay-mode") {
+        outbound.insert(String::from("udp_relay_mode"), json!(mode));
+    }
+    if flag(proxy, "reduce-rtt") {
+        outbound.insert(String::from("zero_rtt_handshake"), json!(true));
+    }
+    let mut tls = tls(proxy);
+    if flag(proxy, "disable-sni") {
+        tls["disable_sni"] = json!(true);
+    }
+    outbound.insert(String::from("tls"), tls);
+    Ok(Value::Object(outbound))
+}
+
+/// `socks5` and `http` proxies, optionally with credentials and TLS.
+fn plain(proxy: &Value, protocol: &str) -> Result<Value> {
+    let mut outbound = outbound(proxy, protocol)?;
+    if let Some(username) = field(proxy, "username") {
+        outbound.insert(String::from("username"), json!(username));
+    }
+    if let Some(password) = field(proxy, "password") {
+        outbound.insert(String::from("password"), json!(password));
+    }
+    if flag(proxy, "tls") {
+        outbound.insert(String::from("tls"), tls(proxy));
+    }
+    Ok(Value::Object(outbound))
+}
+
+/// Convert one Clash proxy into a sing-box outbound.
+fn parse_proxy(proxy: &Value) -> Result<Value> {
+    match field(proxy, "type").as_deref() {
+        Some("ss") => shadowsocks(proxy),
+        Some("vmess") => vmess(proxy),
+        Some("vless") => vless(proxy),
+        Some("trojan") => trojan(proxy),
+        Some("hysteria2") => hysteria2(proxy),
+        Some("tuic") => tuic(proxy),
+        Some("socks5") => plain(proxy, "socks"),
+        Some("http") => plain(proxy, "http"),
+        Some(protocol) => Err(Error::Parse(format!("Unsupported proxy type {protocol}"))),
+        None => Err(Error::Parse(String::from("Proxy without type"))),
+    }
+}
+
+/// Build a config from the `proxies` of a Clash YAML subscription.
+pub fn parse_clash(body: &str) -> Result<Config> {
+    let yaml: serde_yaml::Value =
+        serde_yaml::from_str(body).map_err(|e| Error::Parse(format!("Invalid YAML: {e}")))?;
+    let yaml: Value = serde_json::to_value(yaml)?;
+    let proxies = yaml
+        .get("proxies")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::Parse(String::from("Clash subscription has no proxies.")))?;
+
+    let mut outbounds = Vec::new();
+    for proxy in proxies {
+        match parse_proxy(proxy) {
+            Ok(outbound) => outbounds.push(outbound),
+            Err(e) => warn!("✖ Skipped Clash proxy: {e}"),
+        }
+    }
+    if outbounds.is_empty() {
+        return Err(Error::Parse(String::from(
+            "Clash subscription has no supported proxies.",
+        )));
+    }
+    debug!("Parsed {} Clash proxies", outbounds.len());
+    Ok(node_config(outbounds))
+}
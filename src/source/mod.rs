@@ -0,0 +1,635 @@
+use std::{
+    env, fs,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use futures_util::future::try_join_all;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rand::Rng;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT,
+    },
+    redirect::Policy,
+    Client, Proxy, Response, StatusCode,
+};
+use serde_json::Value;
+use tracing::{debug, info, trace, warn};
+use url::{Host, Url};
+
+use crate::{convert::Node, encryption::Encryption, Config, Error, Result, Target};
+
+pub mod cache;
+mod clash;
+mod links;
+mod quota;
+
+use cache::CacheEntry;
+pub use clash::parse_clash;
+pub use links::{parse_body, parse_link, parse_links, sniff, BodyFormat};
+pub use quota::{format_bytes, Quota};
+
+/// How subscriptions and rule-sets are requested.
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    pub user_agent: String,
+    /// Extra headers sent with subscription requests only, never to rule-set hosts.
+    pub headers: Vec<(String, String)>,
+    /// Token sent as `Authorization: Bearer <token>` with subscription requests.
+    pub bearer: Option<String>,
+    /// Extra attempts after a retryable failure (5xx, 429, timeouts, connection errors).
+    pub retries: u32,
+    /// Base delay, doubled on every retry with up to 50% jitter.
+    pub retry_delay: Duration,
+    /// Proxy for every request, e.g. `socks5://127.0.0.1:1080`.
+    ///
+    /// Without it the `HTTP_PROXY`/`HTTPS_PROXY`/`ALL_PROXY` variables are honored.
+    pub proxy: Option<String>,
+    /// Total time allowed per request, `None` waits forever.
+    pub timeout: Option<Duration>,
+    /// Cache subscription responses here and send conditional requests.
+    pub cache_dir: Option<PathBuf>,
+    /// Ignore the cache validators and always fetch the full response.
+    pub force: bool,
+    /// Use the cached response when the network fetch fails.
+    pub offline_fallback: bool,
+    /// Redirects followed per request before giving up.
+    pub max_redirects: usize,
+    /// Refuse plain-HTTP urls, redirect targets included.
+    pub https_only: bool,
+    /// Accept invalid or self-signed TLS certificates.
+    pub insecure: bool,
+    /// Encrypt cached responses with this key.
+    pub encryption: Option<Encryption>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            user_agent: Target::SingBox.default_user_agent().to_string(),
+            headers: Vec::new(),
+            bearer: None,
+            retries: 2,
+            retry_delay: Duration::from_secs(1),
+            proxy: None,
+            timeout: Some(Duration::from_secs(30)),
+            cache_dir: Some(cache::default_cache_dir()),
+            force: false,
+            offline_fallback: false,
+            max_redirects: 10,
+            https_only: false,
+            insecure: false,
+            encryption: None,
+        }
+    }
+}
+
+pub(crate) fn build_client(options: &FetchOptions, headers: HeaderMap) -> Result<Client> {
+    let (max_redirects, https_only) = (options.max_redirects, options.https_only);
+    let redirect = Policy::custom(move |attempt| {
+        if https_only && attempt.url().scheme() != "https" {
+            let url = redact_url(attempt.url().as_str());
+            attempt.error(format!(
+                "refusing redirect to plain HTTP {url} (--https-only)"
+            ))
+        } else if attempt.previous().len() > max_redirects {
+            attempt.error(format!(
+                "redirect chain longer than {max_redirects} hops, raise --max-redirects to follow it"
+            ))
+        } else {
+            attempt.follow()
+        }
+    });
+    let mut builder = Client::builder()
+        .default_headers(headers)
+        .redirect(redirect)
+        .https_only(https_only)
+        .danger_accept_invalid_certs(options.insecure);
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(proxy) = &options.proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| Error::Validation(format!("Invalid proxy {proxy}: {e}")))?;
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder.build()?)
+}
+
+fn backoff_delay(base: Duration, attempt: u32) -> Duration {
+    let delay = base.saturating_mul(2u32.saturating_pow(attempt));
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.5))
+}
+
+pub(crate) async fn send_with_retry(
+    client: &Client,
+    url: &str,
+    headers: &HeaderMap,
+    options: &FetchOptions,
+) -> Result<Response> {
+    let mut attempt = 0;
+    loop {
+        let result = client.get(url).headers(headers.clone()).send().await;
+        match &result {
+            Ok(response) => {
+                debug!("GET {} -> {}", redact_url(url), response.status());
+                for (name, value) in response.headers() {
+                    debug!("< {name}: {}", value.to_str().unwrap_or("<binary>"));
+                }
+            }
+            Err(e) => debug!("GET {} failed: {}", redact_url(url), redact_error(e)),
+        }
+        let retryable = match &result {
+            Ok(response) => {
+                response.status().is_server_error()
+                    || response.status() == StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+        };
+        if !retryable || attempt >= options.retries {
+            return Ok(result?);
+        }
+
+        let delay = backoff_delay(options.retry_delay, attempt);
+        attempt += 1;
+        let reason = match &result {
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) => redact_error(e),
+        };
+        warn!(
+            "✖ Fetch failed ({reason}), retry {attempt}/{} in {:.1}s...",
+            options.retries,
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn progress() -> &'static MultiProgress {
+    static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+    PROGRESS.get_or_init(|| {
+        // Bars would only garble logs redirected to files or journals.
+        if std::io::stdout().is_terminal() {
+            MultiProgress::with_draw_target(ProgressDrawTarget::stderr())
+        } else {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        }
+    })
+}
+
+/// Read a response body, showing its size and speed in a progress bar.
+pub(crate) async fn read_body(mut response: Response, label: &str) -> Result<Vec<u8>> {
+    let bar = progress().add(match response.content_length() {
+        Some(length) => ProgressBar::new(length).with_style(
+            ProgressStyle::with_template(
+                "{msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec} {eta}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        ),
+        None => ProgressBar::no_length()
+            .with_style(ProgressStyle::with_template("{msg} {bytes} {bytes_per_sec}").unwrap()),
+    });
+    bar.set_message(label.to_string());
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        bar.inc(chunk.len() as u64);
+    }
+    bar.finish_and_clear();
+    progress().remove(&bar);
+    Ok(body)
+}
+
+/// A fetched subscription and its sing-box config.
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub url: String,
+    pub config: Config,
+    /// The provider answered 304 and `config` comes from the cache.
+    pub not_modified: bool,
+    pub quota: Option<Quota>,
+}
+
+impl Subscription {
+    pub async fn fetch(url: &str, options: &FetchOptions) -> Result<Self> {
+        fetch_subscription(url, options).await
+    }
+
+    fn from_cache(url: &str, entry: &CacheEntry, not_modified: bool) -> Result<Self> {
+        Ok(Subscription {
+            url: url.to_string(),
+            config: parse_body(&entry.body, entry.content_type.as_deref())?,
+            not_modified,
+            quota: entry.userinfo.as_deref().and_then(Quota::parse),
+        })
+    }
+
+    /// Fetch several subscriptions concurrently, failing on the first error.
+    pub async fn fetch_all(urls: &[String], options: &FetchOptions) -> Result<Vec<Self>> {
+        try_join_all(urls.iter().map(|url| Self::fetch(url, options))).await
+    }
+
+    /// Merge subscriptions into the first one by appending the others' outbounds.
+    ///
+    /// Outbounds whose tag is already taken get the subscription index appended.
+    pub fn merge(subscriptions: Vec<Subscription>) -> Option<Subscription> {
+        let mut subscriptions = subscriptions.into_iter();
+        let mut merged = subscriptions.next()?;
+        let mut tags: Vec<String> = merged.nodes().into_iter().map(|node| node.tag).collect();
+        for (index, subscription) in subscriptions.enumerate() {
+            merged.not_modified &= subscription.not_modified;
+            for mut node in subscription.nodes() {
+                if tags.contains(&node.tag) {
+                    node.tag = format!("{} ({})", node.tag, index + 2);
+                    node.outbound["tag"] = Value::String(node.tag.clone());
+                }
+                tags.push(node.tag);
+                let outbounds = merged
+                    .config
+                    .entry(String::from("outbounds"))
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                if let Some(outbounds) = outbounds.as_array_mut() {
+                    outbounds.push(node.outbound);
+                }
+            }
+        }
+        Some(merged)
+    }
+
+    pub fn nodes(&self) -> Vec<Node> {
+        self.config
+            .get("outbounds")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Node::from_outbound)
+            .collect()
+    }
+}
+
+/// Validate a subscription url, returning it unchanged.
+///
+/// Plain-HTTP urls and IP or bare local hosts are only accepted when the host is
+/// listed in `allowed_hosts` (`*` allows any host).
+pub fn check_url(sub_url: &str, allowed_hosts: &[String]) -> Result<String> {
+    let sub_url = sub_url.trim();
+    let parsed_url =
+        Url::parse(sub_url).map_err(|e| Error::Url(format!("URL parse failed: {e}")))?;
+
+    if !["http", "https"].contains(&parsed_url.scheme()) {
+        return Err(Error::Url(String::from("Only support http or https.")));
+    }
+
+    let Some(host) = parsed_url.host() else {
+        return Err(Error::Url(String::from("Invalid url without host name.")));
+    };
+    let host_name = parsed_url.host_str().unwrap_or_default();
+    let allowed = allowed_hosts.iter().any(|allowed| {
+        allowed == "*" || allowed.eq_ignore_ascii_case(host_name.trim_matches(['[', ']']))
+    });
+
+    // Bare names such as `localhost` or `router` only resolve on the local network.
+    let local = match host {
+        Host::Domain(domain) => !domain.contains('.'),
+        Host::Ipv4(_) | Host::Ipv6(_) => true,
+    };
+    if local && !allowed {
+        return Err(Error::Url(format!(
+            "Local or IP host {host_name} is not allowed, pass --allow-host {} to use it.",
+            host_name.trim_matches(['[', ']'])
+        )));
+    }
+    if parsed_url.scheme() == "http" && !allowed {
+        return Err(Error::Url(format!(
+            "Plain HTTP is not allowed for {host_name}, pass --allow-host {host_name} or use https."
+        )));
+    }
+
+    Ok(sub_url.to_string())
+}
+
+fn looks_secret(segment: &str) -> bool {
+    let has_digit = segment.chars().any(|c| c.is_ascii_digit());
+    let has_alpha = segment.chars().any(|c| c.is_ascii_alphabetic());
+    segment.len() >= 16 || (segment.len() >= 8 && has_digit && has_alpha)
+}
+
+/// Message of a reqwest error with its url redacted.
+pub(crate) fn redact_error(e: &reqwest::Error) -> String {
+    match e.url() {
+        Some(url) => e
+            .to_string()
+            .replace(url.as_str(), &redact_url(url.as_str())),
+        None => e.to_string(),
+    }
+}
+
+/// Mask credentials, query values and token-like path segments of a url for display.
+///
+/// Unparseable input is masked completely.
+pub fn redact_url(url: &str) -> String {
+    const MASK: &str = "***";
+    let Ok(mut parsed) = Url::parse(url) else {
+        return String::from(MASK);
+    };
+    if !parsed.username().is_empty() {
+        let _ = parsed.set_username(MASK);
+    }
+    if parsed.password().is_some() {
+        let _ = parsed.set_password(Some(MASK));
+    }
+
+    let path = parsed
+        .path()
+        .split('/')
+        .map(|segment| if looks_secret(segment) { MASK } else { segment })
+        .collect::<Vec<_>>()
+        .join("/");
+    parsed.set_path(&path);
+
+    let keys: Vec<String> = parsed
+        .query_pairs()
+        .map(|(key, _)| key.into_owned())
+        .collect();
+    if !keys.is_empty() {
+        let query = keys
+            .iter()
+            .map(|key| format!("{key}={MASK}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        parsed.set_query(Some(&query));
+    }
+    if parsed.fragment().is_some() {
+        parsed.set_fragment(Some(MASK));
+    }
+    parsed.to_string()
+}
+
+fn conditional_headers(entry: &CacheEntry) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in [
+        (IF_NONE_MATCH, &entry.etag),
+        (IF_MODIFIED_SINCE, &entry.last_modified),
+    ] {
+        if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers
+}
+
+async fn fetch_subscription(sub_url: &str, options: &FetchOptions) -> Result<Subscription> {
+    // Unparseable urls fail with a better message when fetched.
+    let plain = Url::parse(sub_url).is_ok_and(|url| url.scheme() != "https");
+    if options.https_only && plain {
+        return Err(Error::Url(format!(
+            "Plain HTTP is refused with --https-only: {}",
+            redact_url(sub_url)
+        )));
+    }
+    if options.insecure {
+        warn!(
+            "✖ Not verifying the TLS certificate of {}",
+            redact_url(sub_url)
+        );
+    }
+    let cached = options
+        .cache_dir
+        .as_deref()
+        .and_then(|dir| cache::load(dir, sub_url, options.encryption.as_ref()));
+
+    match (
+        fetch_remote(sub_url, cached.as_ref(), options).await,
+        cached,
+    ) {
+        (Err(Error::Network(e)), Some(entry)) if options.offline_fallback => {
+            warn!("✖ Fetch failed: {e}");
+            warn!(
+                "✖ Falling back to cached subscription fetched {} ago.",
+                cache::format_age(cache::now().saturating_sub(entry.fetched_at))
+            );
+            Subscription::from_cache(sub_url, &entry, false)
+        }
+        (result, _) => result,
+    }
+}
+
+async fn fetch_remote(
+    sub_url: &str,
+    cached: Option<&CacheEntry>,
+    options: &FetchOptions,
+) -> Result<Subscription> {
+    let mut headers = HeaderMap::new();
+    let user_agent = HeaderValue::from_str(&options.user_agent)
+        .map_err(|e| Error::Validation(format!("Invalid user agent: {e}")))?;
+    headers.insert(USER_AGENT, user_agent);
+    for (name, value) in &options.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| Error::Validation(format!("Invalid header name {name}: {e}")))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| Error::Validation(format!("Invalid value for header {name}: {e}")))?;
+        headers.append(name, value);
+    }
+    if let Some(token) = &options.bearer {
+        let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+            .map_err(|e| Error::Validation(format!("Invalid bearer token: {e}")))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    let client = build_client(options, headers)?;
+
+    let conditional = match cached {
+        Some(entry) if !options.force => conditional_headers(entry),
+        _ => HeaderMap::new(),
+    };
+    let response = send_with_retry(&client, sub_url, &conditional, options).await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(entry) = cached {
+            return Subscription::from_cache(sub_url, entry, true);
+        }
+    }
+
+    if response.status().is_success() {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &HeaderValue| v.to_str().ok())
+                .map(String::from)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let userinfo = header(HeaderName::from_static("subscription-userinfo"));
+        let content_type = header(CONTENT_TYPE);
+        let quota = userinfo.as_deref().and_then(Quota::parse);
+        let body = read_body(response, "subscription").await?;
+        debug!("Subscription body is {} bytes", body.len());
+        trace!("Subscription body: {}", String::from_utf8_lossy(&body));
+        let config = parse_body(&body, content_type.as_deref())?;
+
+        if let Some(dir) = options.cache_dir.as_deref() {
+            let entry = CacheEntry {
+                body,
+                etag,
+                last_modified,
+                userinfo,
+                content_type,
+                fetched_at: cache::now(),
+            };
+            if let Err(e) = cache::store(dir, sub_url, &entry, options.encryption.as_ref()) {
+                warn!("✖ Can't cache subscription: {e}");
+            }
+        }
+        Ok(Subscription {
+            url: sub_url.to_string(),
+            config,
+            not_modified: false,
+            quota,
+        })
+    } else {
+        Err(Error::Network(format!(
+            "Error fetching subscription: HTTP {}",
+            response.status()
+        )))
+    }
+}
+
+struct RemoteRuleSet {
+    index: usize,
+    tag: String,
+    url: String,
+    format: String,
+}
+
+async fn download_rule_set(
+    client: &Client,
+    rule_set: &RemoteRuleSet,
+    rule_set_dir: &Path,
+    options: &FetchOptions,
+) -> Result<PathBuf> {
+    let response = send_with_retry(client, &rule_set.url, &HeaderMap::new(), options).await?;
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Error fetching rule-set {}: HTTP {}",
+            rule_set.tag,
+            response.status()
+        )));
+    }
+    let content = read_body(response, &rule_set.tag).await?;
+
+    let extension = if rule_set.format == "source" {
+        "json"
+    } else {
+        "srs"
+    };
+    let path = rule_set_dir.join(format!("{}.{extension}", rule_set.tag));
+    fs::write(&path, &content)?;
+    info!(
+        "✅ Downloaded rule-set {} to: {}",
+        rule_set.tag,
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Download every remote rule-set concurrently and reference the local copies.
+pub async fn download_rule_sets(
+    data: &mut Config,
+    rule_set_dir: &Path,
+    options: &FetchOptions,
+) -> Result<usize> {
+    let Some(rule_sets) = data
+        .get_mut("route")
+        .and_then(|route| route.get_mut("rule_set"))
+        .and_then(Value::as_array_mut)
+    else {
+        return Ok(0);
+    };
+
+    let mut remotes = Vec::new();
+    for (index, rule_set) in rule_sets.iter().enumerate() {
+        if rule_set.get("type").and_then(Value::as_str) != Some("remote") {
+            continue;
+        }
+        let (Some(tag), Some(url)) = (
+            rule_set.get("tag").and_then(Value::as_str),
+            rule_set.get("url").and_then(Value::as_str),
+        ) else {
+            return Err(Error::Validation(format!(
+                "Remote rule-set without tag or url: {rule_set}"
+            )));
+        };
+        // The tag names the local file, it must not point outside the rule-set dir.
+        if tag.is_empty() || tag.contains(['/', '\\', ':']) || tag.contains("..") {
+            return Err(Error::Validation(format!(
+                "Rule-set tag {tag:?} can't be used as a file name."
+            )));
+        }
+        let format = rule_set
+            .get("format")
+            .and_then(Value::as_str)
+            .unwrap_or("binary");
+        remotes.push(RemoteRuleSet {
+            index,
+            tag: tag.to_string(),
+            url: url.to_string(),
+            format: format.to_string(),
+        });
+    }
+    if remotes.is_empty() {
+        return Ok(0);
+    }
+
+    let rule_set_dir = env::current_dir()?.join(rule_set_dir);
+    fs::create_dir_all(&rule_set_dir)?;
+    let client = build_client(options, HeaderMap::new())?;
+    let paths = try_join_all(
+        remotes
+            .iter()
+            .map(|remote| download_rule_set(&client, remote, &rule_set_dir, options)),
+    )
+    .await?;
+
+    for (remote, path) in remotes.iter().zip(paths) {
+        rule_sets[remote.index] = serde_json::json!({
+            "type": "local",
+            "tag": remote.tag,
+            "format": remote.format,
+            "path": path.display().to_string(),
+        });
+    }
+    Ok(remotes.len())
+}
+
+/// Country database used when no other one is given.
+pub const DEFAULT_GEOIP_URL: &str =
+    "https://github.com/Loyalsoldier/geoip/releases/latest/download/Country.mmdb";
+
+/// Download the GeoIP database to `path` unless it already exists.
+pub async fn download_geoip(path: &Path, options: &FetchOptions) -> Result<()> {
+    if path.is_file() {
+        return Ok(());
+    }
+    let client = build_client(options, HeaderMap::new())?;
+    let response = send_with_retry(&client, DEFAULT_GEOIP_URL, &HeaderMap::new(), options).await?;
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Error fetching GeoIP database: HTTP {}",
+            response.status()
+        )));
+    }
+    let content = read_body(response, "GeoIP").await?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, &content)?;
+    info!("✅ Downloaded GeoIP database to: {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+use super::cache::format_age;
+
+/// Traffic quota announced by the provider in the `subscription-userinfo` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Quota {
+    pub upload: u64,
+    pub download: u64,
+    pub total: u64,
+    /// Unix seconds the plan expires at, `None` for no expiry.
+    pub expire: Option<u64>,
+}
+
+impl Quota {
+    /// Parse `upload=1234; download=5678; total=10240; expire=1700000000`.
+    pub fn parse(header: &str) -> Option<Quota> {
+        let mut quota = Quota::default();
+        let mut found = false;
+        for pair in header.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            // Some providers send floats or an empty expire.
+            let value = value.trim().parse::<f64>().ok().map(|v| v as u64);
+            match (key.trim(), value) {
+                ("upload", Some(value)) => quota.upload = value,
+                ("download", Some(value)) => quota.download = value,
+                ("total", Some(value)) => quota.total = value,
+                ("expire", value) => quota.expire = value.filter(|v| *v > 0),
+                _ => continue,
+            }
+            found = true;
+        }
+        found.then_some(quota)
+    }
+
+    pub fn used(&self) -> u64 {
+        self.upload.saturating_add(self.download)
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.total.saturating_sub(self.used())
+    }
+
+    /// Warnings when the plan expires within `expiry_days` or used `traffic_percent` of
+    /// its traffic, as of the unix seconds `now`.
+    pub fn alerts(
+        &self,
+        now: u64,
+        expiry_days: Option<u64>,
+        traffic_percent: Option<f64>,
+    ) -> Vec<String> {
+        let mut alerts = Vec::new();
+        if let (Some(expire), Some(days)) = (self.expire, expiry_days) {
+            if expire <= now {
+                alerts.push(String::from("Subscription plan has expired."));
+            } else if expire - now < days.saturating_mul(86400) {
+                alerts.push(format!(
+                    "Subscription plan expires in {}.",
+                    format_age(expire - now)
+                ));
+            }
+        }
+        if let Some(percent) = traffic_percent.filter(|_| self.total > 0) {
+            let used = self.used() as f64 * 100.0 / self.total as f64;
+            if used >= percent {
+                alerts.push(format!(
+                    "Used {used:.1}% of the traffic, {} left.",
+                    format_bytes(self.remaining())
+                ));
+            }
+        }
+        alerts
+    }
+}
+
+/// Format a byte count with binary units, e.g. `12.34 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use reqwest::{Client, Proxy};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{debug, info};
+
+use crate::{Config, Error, Node, Result};
+
+/// How the throughput of each node is measured.
+#[derive(Debug, Clone)]
+pub struct SpeedTestOptions {
+    /// Downloaded through every node, should be larger than `duration` worth of data.
+    pub url: String,
+    /// Stop downloading after this long.
+    pub duration: Duration,
+}
+
+/// Throughput of one node in bytes per second, or why it couldn't be measured.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeedResult {
+    pub tag: String,
+    pub speed: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// A sing-box process killed on drop, with its temporary config.
+struct Instance {
+    child: Child,
+    config_path: PathBuf,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_file(&self.config_path);
+    }
+}
+
+fn free_port() -> Result<u16> {
+    Ok(std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port())
+}
+
+/// Start sing-box routing everything from a local mixed inbound through `node`.
+async fn start(sing_box: &Path, node: &Node, port: u16) -> Result<Instance> {
+    let config = json!({
+        "log": { "level": "warn" },
+        "inbounds": [{ "type": "mixed", "tag": "speedtest-in", "listen": "127.0.0.1", "listen_port": port }],
+        "outbounds": [node.outbound],
+        "route": { "final": node.tag },
+    });
+    let config_path = env::temp_dir().join(format!("external-convertor-speedtest-{port}.json"));
+    fs::write(&config_path, serde_json::to_vec(&config)?)?;
+
+    let child = Command::new(sing_box)
+        .arg("run")
+        .arg("-c")
+        .arg(&config_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Install(format!("Can't run sing-box: {e}")))?;
+    let mut instance = Instance { child, config_path };
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if let Some(status) = instance.child.try_wait()? {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = instance.child.stderr.take() {
+                std::io::Read::read_to_string(&mut pipe, &mut stderr)?;
+            }
+            return Err(Error::Install(format!(
+                "sing-box exited with {status}: {}",
+                stderr.trim()
+            )));
+        }
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            return Ok(instance);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err(Error::Install(String::from(
+        "sing-box didn't start listening within 5s.",
+    )))
+}
+
+/// Download through the proxy on `port` for up to `duration`, returning bytes per second.
+async fn measure(port: u16, options: &SpeedTestOptions) -> Result<f64> {
+    let client = Client::builder()
+        .proxy(Proxy::all(format!("http://127.0.0.1:{port}"))?)
+        .connect_timeout(Duration::from_secs(10))
+        .build()?;
+    let start = Instant::now();
+    let mut response = client.get(&options.url).send().await?;
+    if !response.status().is_success() {
+        return Err(Error::Network(format!("HTTP {}", response.status())));
+    }
+
+    let mut bytes = 0u64;
+    while let Ok(chunk) = tokio::time::timeout(
+        options.duration.saturating_sub(start.elapsed()),
+        response.chunk(),
+    )
+    .await
+    {
+        match chunk? {
+            Some(chunk) => bytes += chunk.len() as u64,
+            None => break,
+        }
+    }
+    Ok(bytes as f64 / start.elapsed().as_secs_f64())
+}
+
+async fn test_node(sing_box: &Path, node: &Node, options: &SpeedTestOptions) -> Result<f64> {
+    let port = free_port()?;
+    let _instance = start(sing_box, node, port).await?;
+    measure(port, options).await
+}
+
+/// Measure every node of `config` one after another, fastest first.
+pub async fn speed_test(
+    sing_box: &Path,
+    config: &Config,
+    options: &SpeedTestOptions,
+) -> Vec<SpeedResult> {
+    let nodes: Vec<Node> = config
+        .get("outbounds")
+        .and_then(|outbounds| outbounds.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Node::from_outbound)
+        .collect();
+
+    let mut results = Vec::new();
+    for (index, node) in nodes.iter().enumerate() {
+        info!("✅ Testing {}/{}: {}", index + 1, nodes.len(), node.tag);
+        let speed = test_node(sing_box, node, options).await;
+        debug!("{}: {speed:?}", node.tag);
+        let (speed, error) = match speed {
+            Ok(speed) => (Some(speed), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        results.push(SpeedResult {
+            tag: node.tag.clone(),
+            speed,
+            error,
+        });
+    }
+    // Failed nodes sort last, keeping their original order.
+    results.sort_by(|a, b| b.speed.unwrap_or(-1.0).total_cmp(&a.speed.unwrap_or(-1.0)));
+    results
+}